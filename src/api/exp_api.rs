@@ -1,4 +1,4 @@
-use actix_web::{get, http::header::ContentType, HttpRequest, HttpResponse};
+use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
 use log::{debug, error};
 use prometheus::Encoder;
 use regex::Regex;
@@ -7,7 +7,6 @@ use std::{
     collections::HashMap,
     fs::{self, File},
     path::Path,
-    process::exit,
 };
 use walkdir::WalkDir;
 
@@ -15,10 +14,11 @@ use crate::exporter::extractor::{get_megastructures, get_wars};
 use crate::singletons::singletons::get_game_data;
 use crate::{
     exporter::{
-        exporter::{REGISTRY, STELLARIS_INCOMING_REQUESTS},
+        exporter::{gather_metrics, STELLARIS_INCOMING_REQUESTS, STELLARIS_SAVE_PARSE_FAILURES},
         extractor::get_country_infos,
     },
     file::save_handler,
+    history::store::{query_history, HistoryQuery},
 };
 
 fn get_localization_files(dir: &Path) -> Vec<String> {
@@ -124,7 +124,7 @@ pub async fn metrics(_req: HttpRequest) -> HttpResponse {
     let encoder = prometheus::TextEncoder::new();
 
     let mut buffer = Vec::new();
-    if let Err(e) = encoder.encode(&REGISTRY.gather(), &mut buffer) {
+    if let Err(e) = encoder.encode(&gather_metrics(), &mut buffer) {
         eprintln!("could not encode custom metrics: {}", e);
     };
     let mut res = match String::from_utf8(buffer.clone()) {
@@ -155,6 +155,24 @@ pub async fn metrics(_req: HttpRequest) -> HttpResponse {
         .body(res);
 }
 
+#[get("/history")]
+pub async fn history(query: web::Query<HistoryQuery>) -> HttpResponse {
+    STELLARIS_INCOMING_REQUESTS.inc();
+
+    match query_history(&query) {
+        Some(Ok(samples)) => HttpResponse::Ok().json(samples),
+        Some(Err(e)) => {
+            error!("Could not query the history: {}", e);
+            HttpResponse::InternalServerError()
+                .content_type(ContentType::plaintext())
+                .body(format!("Could not query the history: {}", e))
+        }
+        None => HttpResponse::NotFound()
+            .content_type(ContentType::plaintext())
+            .body("History is disabled"),
+    }
+}
+
 #[get("/parse")]
 pub async fn parse(_req: HttpRequest) -> HttpResponse {
     STELLARIS_INCOMING_REQUESTS.inc();
@@ -163,8 +181,13 @@ pub async fn parse(_req: HttpRequest) -> HttpResponse {
     let content = match save_handler::parse_save_file(save_path) {
         Ok(m) => m,
         Err(e) => {
-            println!("Error parsing file: {}", e);
-            exit(1)
+            error!("Error parsing file: {}", e);
+            STELLARIS_SAVE_PARSE_FAILURES
+                .with_label_values(&[e.reason()])
+                .inc();
+            return HttpResponse::InternalServerError()
+                .content_type(ContentType::plaintext())
+                .body(format!("Error parsing file: {}", e));
         }
     };
 
@@ -174,7 +197,7 @@ pub async fn parse(_req: HttpRequest) -> HttpResponse {
     let v: serde_json::Value = serde_json::from_str(&content.gamestate).unwrap();
     let s = serde_json::to_string_pretty(&v).unwrap();
 
-    let _ = save_handler::save_json_to_file(&Box::new(s));
+    let _ = save_handler::save_json_to_file(&s);
     return HttpResponse::Ok()
         .content_type(ContentType::plaintext())
         .body("Done!");
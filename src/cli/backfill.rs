@@ -0,0 +1,195 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+use crate::{
+    exporter::{
+        exporter::gather_campaign,
+        openmetrics::{write_openmetrics, Snapshot},
+    },
+    file::ingest::{export_save, is_save_file, load_save},
+    models::game_date::GameDate,
+};
+
+pub struct BackfillOptions {
+    pub campaign: PathBuf,
+    pub output: PathBuf,
+    /// Unix timestamp of 2200.01.01, derived from the newest save when missing.
+    pub anchor: Option<i64>,
+    /// Real seconds elapsed for every in-game day.
+    pub day_seconds: i64,
+}
+
+impl BackfillOptions {
+    pub fn from_args(args: &[String]) -> Result<BackfillOptions, String> {
+        let mut campaign = None;
+        let mut output = None;
+        let mut anchor = None;
+        let mut day_seconds = 60;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value for {}", name))
+            };
+            match arg.as_str() {
+                "--output" => output = Some(PathBuf::from(value(arg)?)),
+                "--anchor" => {
+                    anchor = Some(
+                        value(arg)?
+                            .parse()
+                            .map_err(|_| "--anchor must be a unix timestamp".to_string())?,
+                    )
+                }
+                "--day-seconds" => {
+                    day_seconds = value(arg)?
+                        .parse()
+                        .map_err(|_| "--day-seconds must be a number".to_string())?
+                }
+                _ if campaign.is_none() => campaign = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+
+        let campaign = campaign.ok_or("Missing the campaign directory")?;
+        let output = output.unwrap_or_else(|| {
+            let name = campaign
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("campaign");
+            PathBuf::from(format!("{}.openmetrics.txt", name))
+        });
+        Ok(BackfillOptions {
+            campaign,
+            output,
+            anchor,
+            day_seconds,
+        })
+    }
+}
+
+/// Saves of the campaign, ordered by the in-game date of their file name.
+fn campaign_saves(campaign: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut saves: Vec<PathBuf> = fs::read_dir(campaign)
+        .map_err(|e| format!("Could not read {:?}: {}", campaign, e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_save_file(path))
+        .collect();
+    saves.sort_by_key(|path| {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        (GameDate::from_file_name(name), name.to_string())
+    });
+    Ok(saves)
+}
+
+pub fn backfill(options: &BackfillOptions) -> Result<(), String> {
+    let saves = campaign_saves(&options.campaign)?;
+    info!(
+        "Backfilling {} saves from {:?}",
+        saves.len(),
+        options.campaign
+    );
+
+    let mut gathered: Vec<(GameDate, Snapshot)> = Vec::new();
+    for path in saves {
+        let save = match load_save(&path, false) {
+            Ok(save) => save,
+            Err(e) => {
+                warn!("Skipping {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let Some(date) = save
+            .date()
+            .and_then(|d| d.parse::<GameDate>().ok())
+            .or_else(|| GameDate::from_file_name(&save.filename))
+        else {
+            warn!("Skipping {:?}: it has no in-game date", path);
+            continue;
+        };
+
+        export_save(&save);
+        // Two saves of the same day would produce conflicting samples.
+        gathered.retain(|(d, _)| *d != date);
+        gathered.push((
+            date,
+            Snapshot {
+                timestamp: 0,
+                families: gather_campaign(&save.game_id),
+            },
+        ));
+        info!("Backfilled {}", date);
+    }
+    gathered.sort_by_key(|(date, _)| *date);
+
+    let Some((newest, _)) = gathered.last() else {
+        return Err(format!("No save could be read from {:?}", options.campaign));
+    };
+    let anchor = options.anchor.unwrap_or_else(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        now - newest.days_since_start() * options.day_seconds
+    });
+
+    let snapshots: Vec<Snapshot> = gathered
+        .into_iter()
+        .map(|(date, mut snapshot)| {
+            snapshot.timestamp = anchor + date.days_since_start() * options.day_seconds;
+            snapshot
+        })
+        .collect();
+
+    let file = File::create(&options.output)
+        .map_err(|e| format!("Could not create {:?}: {}", options.output, e))?;
+    let mut writer = BufWriter::new(file);
+    let written = write_openmetrics(&mut writer, &snapshots)
+        .and_then(|written| writer.flush().map(|_| written))
+        .map_err(|e| format!("Could not write {:?}: {}", options.output, e))?;
+    info!(
+        "Wrote {} samples of {} saves to {:?}",
+        written,
+        snapshots.len(),
+        options.output
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_backfill_options() {
+        let options = BackfillOptions::from_args(&args("saves/empire_123")).unwrap();
+        assert_eq!(options.campaign, PathBuf::from("saves/empire_123"));
+        assert_eq!(options.output, PathBuf::from("empire_123.openmetrics.txt"));
+        assert_eq!(options.anchor, None);
+        assert_eq!(options.day_seconds, 60);
+
+        let options = BackfillOptions::from_args(&args(
+            "--day-seconds 1 saves/empire_123 --anchor 1700000000 --output out.txt",
+        ))
+        .unwrap();
+        assert_eq!(options.output, PathBuf::from("out.txt"));
+        assert_eq!(options.anchor, Some(1700000000));
+        assert_eq!(options.day_seconds, 1);
+
+        assert!(BackfillOptions::from_args(&args("")).is_err());
+        assert!(BackfillOptions::from_args(&args("a b")).is_err());
+        assert!(BackfillOptions::from_args(&args("a --anchor")).is_err());
+    }
+}
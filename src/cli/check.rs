@@ -0,0 +1,50 @@
+use std::{fs, path::Path};
+
+use crate::{
+    file::{error::SaveError, ingest::has_save_extension},
+    file_io::load_save_content,
+    parser::parse_file,
+};
+
+/// Parses every given save or game file and prints where the first syntax
+/// error of each one is. Returns the exit code, 1 when a file failed.
+pub fn check(paths: &[String]) -> Result<i32, String> {
+    if paths.is_empty() {
+        return Err("Missing the files to check".to_string());
+    }
+
+    let mut failed = 0;
+    for path in paths {
+        match check_file(Path::new(path)) {
+            Ok(skipped) if skipped.is_empty() => println!("{}: ok", path),
+            Ok(skipped) => println!("{}: ok, binary {} not checked", path, skipped.join(" and ")),
+            Err(e) => {
+                failed += 1;
+                println!("{}: {}", path, e);
+            }
+        }
+    }
+    Ok(if failed == 0 { 0 } else { 1 })
+}
+
+/// Returns the binary entries of a save, which this parser cannot read.
+fn check_file(path: &Path) -> Result<Vec<&'static str>, String> {
+    if !has_save_extension(path) {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Could not read the file: {}", e))?;
+        return parse_file(&content)
+            .map(|_| Vec::new())
+            .map_err(|e| format!("Syntax error: {}", e));
+    }
+
+    let save = load_save_content(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+    let mut skipped = Vec::new();
+    for (entry, content) in [("meta", &save.meta), ("gamestate", &save.gamestate)] {
+        let Some(text) = content.as_text() else {
+            skipped.push(entry);
+            continue;
+        };
+        parse_file(text).map_err(|error| SaveError::Syntax { entry, error }.to_string())?;
+    }
+    Ok(skipped)
+}
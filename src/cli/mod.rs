@@ -0,0 +1,48 @@
+pub mod backfill;
+pub mod check;
+
+use log::error;
+
+const USAGE: &str = "Usage: stellaris_prometheus_exporter [COMMAND]
+
+Without a command the exporter serves the metrics and watches the save location.
+
+Commands:
+  backfill <campaign_dir> [--output <file>] [--anchor <unix_seconds>] [--day-seconds <n>]
+      Runs every save of a campaign through the extractors and writes an
+      OpenMetrics file for `promtool tsdb create-blocks-from openmetrics`.
+      Each sample is stamped with `anchor + in-game days * day-seconds`.
+      By default a game day lasts 60 seconds and the newest save is stamped
+      with the current time.
+
+  check <file>...
+      Parses saves (.sav) and game or mod files with the built-in parser and
+      prints the line, column and surroundings of the first syntax error of
+      each. Exits with 1 when a file has an error.
+";
+
+/// Runs the subcommand given on the command line and returns its exit code,
+/// or `None` when there is no subcommand and the exporter should serve.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "backfill" => backfill::BackfillOptions::from_args(rest)
+            .and_then(|options| backfill::backfill(&options))
+            .map(|()| 0),
+        "check" => check::check(rest),
+        "help" | "--help" | "-h" => {
+            print!("{}", USAGE);
+            return Some(0);
+        }
+        other => Err(format!("Unknown command: {}", other)),
+    };
+
+    match result {
+        Ok(code) => Some(code),
+        Err(e) => {
+            error!("{}", e);
+            eprint!("{}", USAGE);
+            Some(2)
+        }
+    }
+}
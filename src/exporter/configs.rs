@@ -9,6 +9,12 @@ use toml;
 pub struct Config {
     pub paths: GamePaths,
     pub api: ApiConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub dump: DumpConfig,
 }
 
 impl Default for Config {
@@ -16,6 +22,9 @@ impl Default for Config {
         Self {
             paths: GamePaths::default(),
             api: ApiConfig::default(),
+            history: HistoryConfig::default(),
+            metrics: MetricsConfig::default(),
+            dump: DumpConfig::default(),
         }
     }
 }
@@ -25,6 +34,8 @@ pub struct GamePaths {
     pub save_location: String,
     pub game_files_dir: String,
     pub localisation_path: String,
+    #[serde(default)]
+    pub token_table: String,
 }
 
 impl Default for GamePaths {
@@ -33,6 +44,7 @@ impl Default for GamePaths {
             save_location: String::from(""),
             game_files_dir: String::from(""),
             localisation_path: String::from(""),
+            token_table: String::from(""),
         }
     }
 }
@@ -51,6 +63,38 @@ impl Default for ApiConfig {
         }
     }
 }
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub database: String,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database: String::from("history.db"),
+        }
+    }
+}
+
+/// Opt-in metric families whose cardinality grows with the size of the galaxy.
+#[derive(Deserialize, Default)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub planets: bool,
+    #[serde(default)]
+    pub fleets: bool,
+}
+
+/// Debugging dumps of the parsed gamestate.
+#[derive(Deserialize, Default)]
+pub struct DumpConfig {
+    #[serde(default)]
+    pub json: bool,
+}
 // -------
 pub static CONFIGS: Lazy<Mutex<Config>> = Lazy::new(|| Mutex::new(Config::default()));
 
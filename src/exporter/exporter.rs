@@ -1,14 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, RwLock},
+};
+
 use once_cell::sync::Lazy;
 
-use prometheus::{GaugeVec, IntCounter, IntGaugeVec, Opts, Registry};
+use prometheus::{
+    core::{Collector, MetricVec, MetricVecBuilder},
+    proto::MetricFamily,
+    GaugeVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+};
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
 
-pub static REGISTRY: Lazy<Registry> = Lazy::new(|| Registry::new());
+/// Taken for writing while a campaign's series are replaced and for reading
+/// while the registry is gathered, so a scrape never sees half an ingest.
+static CAMPAIGN_UPDATE: Lazy<RwLock<()>> = Lazy::new(|| RwLock::new(()));
+
+/// Every registered metric labelled by `save_name`.
+static CAMPAIGN_SERIES: Lazy<Mutex<Vec<Box<dyn CampaignSeries + Send>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
 // -----------
 pub static STELLARIS_INCOMING_REQUESTS: Lazy<IntCounter> = Lazy::new(|| {
     IntCounter::new("stellaris_incoming_requests", "Incoming Requests")
         .expect("metric can be created")
 });
 
+pub static STELLARIS_SAVE_PARSE_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "stellaris_save_parse_failures_total",
+            "The amount of save files that could not be parsed, by reason",
+        ),
+        &["reason"],
+    )
+    .expect("metric can be created")
+});
+
+pub static STELLARIS_GAME_DAYS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_game_date_days",
+            "The in-game date as days elapsed since 2200.01.01",
+        ),
+        &["save_name"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GAME_DATE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_game_date",
+            "The in-game date split in its components. (Year, Month, Day)",
+        ),
+        &["save_name", "component"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GAME_TICK: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new("stellaris_game_tick", "The game tick of the save"),
+        &["save_name"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GAME_VERSION: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_game_version_info",
+            "The game version the save was written with, always 1",
+        ),
+        &["save_name", "version"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GAME_REQUIRED_DLCS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_game_required_dlcs",
+            "The DLCs required to load the save, always 1",
+        ),
+        &["save_name", "dlc"],
+    )
+    .expect("Could'nt create gauge")
+});
+
 pub static STELLARIS_COUNTRY_POWER: Lazy<GaugeVec> = Lazy::new(|| {
     GaugeVec::new(
         Opts::new(
@@ -66,6 +146,9 @@ pub static STELLARIS_COUNTRY_BATTLE_LOSSES: Lazy<GaugeVec> = Lazy::new(|| {
             "battle_type",
             "loss",
             "id",
+            "system",
+            "victor",
+            "war_id",
         ],
     )
     .expect("Could'nt create gauge")
@@ -77,7 +160,7 @@ pub static STELLARIS_COUNTRY_SHIP_SIZES: Lazy<GaugeVec> = Lazy::new(|| {
             "stellaris_country_ship_sizes",
             "The amount of ship sizes for each country",
         ),
-        &["save_name", "name", "ship_size"],
+        &["save_name", "name", "ship_size", "fleet_type"],
     )
     .expect("Could'nt create gauge")
 });
@@ -102,6 +185,506 @@ pub static STELLARIS_COUNTRY_WAR_BATLLES: Lazy<IntGaugeVec> = Lazy::new(|| {
     .expect("Could'nt create gauge")
 });
 
+pub static STELLARIS_COUNTRY_POPS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_pops",
+            "The amount of pops of each country by species, job, stratum, ethic and happiness",
+        ),
+        &[
+            "save_name",
+            "country",
+            "species",
+            "job",
+            "stratum",
+            "ethic",
+            "happiness",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_SPECIES_POPS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_species_pops",
+            "The amount of pops of each species in the whole galaxy",
+        ),
+        &["save_name", "species"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_PLANET_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_planet_info",
+            "Class and designation of each colonized planet, always 1",
+        ),
+        &[
+            "save_name",
+            "country",
+            "planet_id",
+            "planet",
+            "class",
+            "designation",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_PLANET_STATS: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_planet_stats",
+            "Size, stability, amenities, crime, housing and jobs of each colonized planet",
+        ),
+        &["save_name", "country", "planet_id", "planet", "stat"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_PLANET_DISTRICTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_planet_districts",
+            "The amount of districts of each type on each colonized planet",
+        ),
+        &["save_name", "country", "planet_id", "planet", "district"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_PLANET_BUILDINGS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_planet_buildings",
+            "The amount of buildings of each type on each colonized planet",
+        ),
+        &["save_name", "country", "planet_id", "planet", "building"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_LEADERS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_leaders",
+            "The amount of leaders of each class employed by each country",
+        ),
+        &["save_name", "country", "class"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_LEADER_LEVEL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new("stellaris_leader_level", "The level of each leader"),
+        &["save_name", "country", "leader_id", "leader", "class"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_LEADER_AGE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new("stellaris_leader_age", "The age in years of each leader"),
+        &["save_name", "country", "leader_id", "leader", "class"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_LEADER_TRAITS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_leader_traits",
+            "How many leaders of each class have each trait, per country",
+        ),
+        &["save_name", "country", "class", "trait"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_LEADER_ASSIGNMENTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_leader_assignments",
+            "How many leaders of each class hold each assignment, per country",
+        ),
+        &["save_name", "country", "class", "assignment"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_RULER: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_ruler_days_in_office",
+            "In-game days since the current ruler of each country took office, from the first ingested save with that ruler",
+        ),
+        &["save_name", "country", "leader_id", "leader", "class"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_WAR_EXHAUSTION: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_war_exhaustion",
+            "The war exhaustion of each side of each war",
+        ),
+        &["save_name", "war_id", "war_name", "side"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_WAR_PARTICIPANTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_war_participants",
+            "Every country taking part in each war, always 1",
+        ),
+        &[
+            "save_name",
+            "war_id",
+            "war_name",
+            "side",
+            "country",
+            "call_type",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_FEDERATION_MEMBERS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_federation_members",
+            "The amount of member countries of each federation",
+        ),
+        &["save_name", "federation", "federation_type", "president"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_FEDERATION_MEMBER: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_federation_member",
+            "Membership of each country in a federation, always 1",
+        ),
+        &["save_name", "federation", "country"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_FEDERATION_PROGRESS: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_federation_progress",
+            "Level, cohesion and experience of each federation",
+        ),
+        &["save_name", "federation", "stat"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GALACTIC_COMMUNITY_MEMBER: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_galactic_community_member",
+            "Members of the galactic community and whether they hold a council seat, always 1",
+        ),
+        &["save_name", "country", "council"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GALACTIC_COMMUNITY_VOTES: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_galactic_community_votes",
+            "Vote weight behind each option of the resolution currently being voted",
+        ),
+        &["save_name", "resolution", "vote"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_GALACTIC_RESOLUTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_galactic_resolutions",
+            "The amount of times each galactic community resolution was passed",
+        ),
+        &["save_name", "resolution"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_MARKET_PRICE: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_market_price",
+            "The galactic market price of each resource, in energy credits",
+        ),
+        &["save_name", "resource"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_MARKET_FLUCTUATION: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_market_fluctuation",
+            "The galactic market price fluctuation of each resource, in percent",
+        ),
+        &["save_name", "resource"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_MARKET_PRICE: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_market_price",
+            "The internal market price of each resource for each country, in energy credits",
+        ),
+        &["save_name", "country", "resource"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_MARKET_TRADE: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_market_trade",
+            "The amount of each resource bought or sold on the market by each country",
+        ),
+        &["save_name", "country", "resource", "direction"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_BUDGET: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_budget",
+            "Monthly income and expenses of each country by source and resource",
+        ),
+        &[
+            "save_name",
+            "country",
+            "month",
+            "direction",
+            "source",
+            "resource",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_NET_BALANCE: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_net_balance",
+            "Monthly income minus expenses of each resource for each country",
+        ),
+        &["save_name", "country", "month", "resource"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_TECHS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_techs",
+            "The amount of technologies researched by each country in each research area",
+        ),
+        &["save_name", "country", "area"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_RESEARCH: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_research_progress",
+            "Research points spent on the technology each country is currently researching",
+        ),
+        &["save_name", "country", "area", "technology"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_REPEATABLE_TECHS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_repeatable_techs",
+            "The level reached by each country in each repeatable technology",
+        ),
+        &["save_name", "country", "technology"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_RESEARCH_ALTERNATIVES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_research_alternatives",
+            "The technologies each country can pick next in each research area, always 1",
+        ),
+        &["save_name", "country", "area", "technology"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_ETHIC_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_ethic_info",
+            "The ethics of each country, always 1",
+        ),
+        &["save_name", "country", "ethic"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_GOVERNMENT_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_government_info",
+            "The government type, authority and origin of each country, always 1",
+        ),
+        &["save_name", "country", "government", "authority", "origin"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_CIVIC_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_civic_info",
+            "The civics of each country, always 1",
+        ),
+        &["save_name", "country", "civic"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_TRADITION_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_tradition_info",
+            "The traditions adopted by each country, always 1",
+        ),
+        &["save_name", "country", "tradition"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_ASCENSION_PERK_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_ascension_perk_info",
+            "The ascension perks picked by each country, always 1",
+        ),
+        &["save_name", "country", "perk"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_FLEET_POWER: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_country_fleet_power",
+            "The military power of the fleets of each country by fleet type",
+        ),
+        &["save_name", "country", "fleet_type"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_FLEET_STATS: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_fleet_stats",
+            "Military power, ship count and command points used by each fleet",
+        ),
+        &[
+            "save_name",
+            "country",
+            "fleet_id",
+            "fleet",
+            "fleet_type",
+            "system",
+            "stat",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_STARBASES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_starbases",
+            "The amount of starbases of each level owned by each country",
+        ),
+        &["save_name", "country", "level"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_STARBASE_MODULES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_starbase_modules",
+            "The amount of starbase modules of each type built by each country",
+        ),
+        &["save_name", "country", "module"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_STARBASE_BUILDINGS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_starbase_buildings",
+            "The amount of starbase buildings of each type built by each country",
+        ),
+        &["save_name", "country", "building"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_COUNTRY_OWNED_SYSTEMS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "stellaris_country_owned_systems",
+            "The amount of systems owned by each country",
+        ),
+        &["save_name", "country"],
+    )
+    .expect("Could'nt create gauge")
+});
+
+pub static STELLARIS_SYSTEM_COORDINATES: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new(
+            "stellaris_system_coordinates",
+            "The galaxy map coordinates of each system, with its owner and starbase level",
+        ),
+        &[
+            "save_name",
+            "system_id",
+            "system",
+            "owner",
+            "starbase_level",
+            "axis",
+        ],
+    )
+    .expect("Could'nt create gauge")
+});
+
 pub static STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new(
@@ -162,38 +745,161 @@ pub fn register_metrics() {
         .register(Box::new(STELLARIS_INCOMING_REQUESTS.clone()))
         .expect("Collector registered");
     REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_POWER.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_BALANCE.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_FLEETS.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_VICTORY_STATUS.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_WAR_ALLIES.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_MEGASTRUCTURES.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_WAR_BATLLES.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(
-            STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES.clone(),
-        ))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_COLONIZED_PLANETS.clone()))
-        .expect("Collector registered");
-    REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_SURVEYED_SYSTEMS.clone()))
+        .register(Box::new(STELLARIS_SAVE_PARSE_FAILURES.clone()))
         .expect("Collector registered");
+    register_campaign_series(&STELLARIS_GAME_DAYS);
+    register_campaign_series(&STELLARIS_GAME_DATE);
+    register_campaign_series(&STELLARIS_GAME_TICK);
+    register_campaign_series(&STELLARIS_GAME_VERSION);
+    register_campaign_series(&STELLARIS_GAME_REQUIRED_DLCS);
+    register_campaign_series(&STELLARIS_COUNTRY_POWER);
+    register_campaign_series(&STELLARIS_COUNTRY_BALANCE);
+    register_campaign_series(&STELLARIS_COUNTRY_FLEETS);
+    register_campaign_series(&STELLARIS_COUNTRY_VICTORY_STATUS);
+    register_campaign_series(&STELLARIS_COUNTRY_WAR_ALLIES);
+    register_campaign_series(&STELLARIS_MEGASTRUCTURES);
+    register_campaign_series(&STELLARIS_COUNTRY_WAR_BATLLES);
+    register_campaign_series(&STELLARIS_COUNTRY_BATTLE_LOSSES);
+    register_campaign_series(&STELLARIS_WAR_EXHAUSTION);
+    register_campaign_series(&STELLARIS_WAR_PARTICIPANTS);
+    register_campaign_series(&STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES);
+    register_campaign_series(&STELLARIS_COUNTRY_COLONIZED_PLANETS);
+    register_campaign_series(&STELLARIS_COUNTRY_SURVEYED_SYSTEMS);
+    register_campaign_series(&STELLARIS_COUNTRY_SHIP_SIZES);
+    register_campaign_series(&STELLARIS_COUNTRY_POPS);
+    register_campaign_series(&STELLARIS_SPECIES_POPS);
+    register_campaign_series(&STELLARIS_PLANET_INFO);
+    register_campaign_series(&STELLARIS_PLANET_STATS);
+    register_campaign_series(&STELLARIS_PLANET_DISTRICTS);
+    register_campaign_series(&STELLARIS_PLANET_BUILDINGS);
+    register_campaign_series(&STELLARIS_COUNTRY_LEADERS);
+    register_campaign_series(&STELLARIS_LEADER_LEVEL);
+    register_campaign_series(&STELLARIS_LEADER_AGE);
+    register_campaign_series(&STELLARIS_COUNTRY_LEADER_TRAITS);
+    register_campaign_series(&STELLARIS_COUNTRY_LEADER_ASSIGNMENTS);
+    register_campaign_series(&STELLARIS_COUNTRY_RULER);
+    register_campaign_series(&STELLARIS_FEDERATION_MEMBERS);
+    register_campaign_series(&STELLARIS_FEDERATION_MEMBER);
+    register_campaign_series(&STELLARIS_FEDERATION_PROGRESS);
+    register_campaign_series(&STELLARIS_GALACTIC_COMMUNITY_MEMBER);
+    register_campaign_series(&STELLARIS_GALACTIC_COMMUNITY_VOTES);
+    register_campaign_series(&STELLARIS_GALACTIC_RESOLUTIONS);
+    register_campaign_series(&STELLARIS_MARKET_PRICE);
+    register_campaign_series(&STELLARIS_MARKET_FLUCTUATION);
+    register_campaign_series(&STELLARIS_COUNTRY_MARKET_PRICE);
+    register_campaign_series(&STELLARIS_COUNTRY_MARKET_TRADE);
+    register_campaign_series(&STELLARIS_COUNTRY_BUDGET);
+    register_campaign_series(&STELLARIS_COUNTRY_NET_BALANCE);
+    register_campaign_series(&STELLARIS_COUNTRY_TECHS);
+    register_campaign_series(&STELLARIS_COUNTRY_RESEARCH);
+    register_campaign_series(&STELLARIS_COUNTRY_REPEATABLE_TECHS);
+    register_campaign_series(&STELLARIS_COUNTRY_RESEARCH_ALTERNATIVES);
+    register_campaign_series(&STELLARIS_COUNTRY_ETHIC_INFO);
+    register_campaign_series(&STELLARIS_COUNTRY_GOVERNMENT_INFO);
+    register_campaign_series(&STELLARIS_COUNTRY_CIVIC_INFO);
+    register_campaign_series(&STELLARIS_COUNTRY_TRADITION_INFO);
+    register_campaign_series(&STELLARIS_COUNTRY_ASCENSION_PERK_INFO);
+    register_campaign_series(&STELLARIS_COUNTRY_FLEET_POWER);
+    register_campaign_series(&STELLARIS_FLEET_STATS);
+    register_campaign_series(&STELLARIS_COUNTRY_STARBASES);
+    register_campaign_series(&STELLARIS_COUNTRY_STARBASE_MODULES);
+    register_campaign_series(&STELLARIS_COUNTRY_STARBASE_BUILDINGS);
+    register_campaign_series(&STELLARIS_COUNTRY_OWNED_SYSTEMS);
+    register_campaign_series(&STELLARIS_SYSTEM_COORDINATES);
+}
+
+/// Registers a metric whose first label is `save_name`, so its series are
+/// replaced as a whole on every ingest of that campaign.
+fn register_campaign_series<T>(metric: &MetricVec<T>)
+where
+    T: MetricVecBuilder + 'static,
+{
     REGISTRY
-        .register(Box::new(STELLARIS_COUNTRY_SHIP_SIZES.clone()))
+        .register(Box::new(metric.clone()))
         .expect("Collector registered");
+    CAMPAIGN_SERIES
+        .lock()
+        .unwrap()
+        .push(Box::new(metric.clone()));
+}
+
+trait CampaignSeries {
+    fn remove_campaign(&self, save: &str);
+}
+
+impl<T: MetricVecBuilder> CampaignSeries for MetricVec<T> {
+    fn remove_campaign(&self, save: &str) {
+        for family in self.collect() {
+            for metric in family.get_metric() {
+                let labels: HashMap<&str, &str> = metric
+                    .get_label()
+                    .iter()
+                    .map(|l| (l.get_name(), l.get_value()))
+                    .collect();
+                if labels.get("save_name") == Some(&save) {
+                    let _ = self.remove(&labels);
+                }
+            }
+        }
+    }
+}
+
+/// Drops every series of the `save` campaign and lets `update` set the new
+/// ones. Series of the other campaigns are kept, and scrapes wait until the
+/// replacement is complete.
+pub fn replace_campaign_series<F: FnOnce()>(save: &str, update: F) {
+    let _guard = CAMPAIGN_UPDATE.write().unwrap_or_else(|e| e.into_inner());
+    for series in CAMPAIGN_SERIES.lock().unwrap().iter() {
+        series.remove_campaign(save);
+    }
+    update();
+}
+
+/// Gathers the custom registry without observing a campaign mid-update.
+pub fn gather_metrics() -> Vec<MetricFamily> {
+    let _guard = CAMPAIGN_UPDATE.read().unwrap_or_else(|e| e.into_inner());
+    REGISTRY.gather()
+}
+
+/// Gathers only the series of the `save` campaign, dropping empty families.
+pub fn gather_campaign(save: &str) -> Vec<MetricFamily> {
+    let mut families = gather_metrics();
+    for family in families.iter_mut() {
+        family.mut_metric().retain(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .any(|l| l.get_name() == "save_name" && l.get_value() == save)
+        });
+    }
+    families.retain(|family| !family.get_metric().is_empty());
+    families
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_campaign_keeps_other_campaigns() {
+        let gauge = GaugeVec::new(Opts::new("test_gauge", "test"), &["save_name", "country"])
+            .expect("metric can be created");
+        gauge.with_label_values(&["campaign_a", "Earth"]).set(1.0);
+        gauge.with_label_values(&["campaign_a", "Blorg"]).set(2.0);
+        gauge.with_label_values(&["campaign_b", "Earth"]).set(3.0);
+
+        gauge.remove_campaign("campaign_a");
+
+        let remaining: Vec<Vec<String>> = gauge.collect()[0]
+            .get_metric()
+            .iter()
+            .map(|m| {
+                m.get_label()
+                    .iter()
+                    .map(|l| l.get_value().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(remaining, vec![vec!["Earth", "campaign_b"]]);
+    }
 }
@@ -1,7 +1,6 @@
-use lazy_static::__Deref;
-use log::{debug, error, info, trace, warn};
-use serde_json::{json, Map, Number, Value};
-use std::{collections::HashMap, error::Error};
+use log::{debug, info, trace, warn};
+use once_cell::sync::Lazy;
+use std::{collections::HashMap, sync::Mutex};
 
 use super::exporter::{
     STELLARIS_COUNTRY_BALANCE, STELLARIS_COUNTRY_BATTLE_LOSSES, STELLARIS_COUNTRY_FLEETS,
@@ -10,460 +9,1282 @@ use super::exporter::{
 };
 use crate::{
     exporter::{
+        configs::MetricsConfig,
         exporter::{
-            STELLARIS_COUNTRY_COLONIZED_PLANETS, STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES,
-            STELLARIS_COUNTRY_SURVEYED_SYSTEMS, STELLARIS_COUNTRY_WAR_ALLIES,
-            STELLARIS_COUNTRY_WAR_BATLLES,
+            STELLARIS_COUNTRY_ASCENSION_PERK_INFO, STELLARIS_COUNTRY_BUDGET,
+            STELLARIS_COUNTRY_CIVIC_INFO, STELLARIS_COUNTRY_COLONIZED_PLANETS,
+            STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES, STELLARIS_COUNTRY_ETHIC_INFO,
+            STELLARIS_COUNTRY_FLEET_POWER, STELLARIS_COUNTRY_GOVERNMENT_INFO,
+            STELLARIS_COUNTRY_LEADERS, STELLARIS_COUNTRY_LEADER_ASSIGNMENTS,
+            STELLARIS_COUNTRY_LEADER_TRAITS, STELLARIS_COUNTRY_MARKET_PRICE,
+            STELLARIS_COUNTRY_MARKET_TRADE, STELLARIS_COUNTRY_NET_BALANCE,
+            STELLARIS_COUNTRY_OWNED_SYSTEMS, STELLARIS_COUNTRY_POPS,
+            STELLARIS_COUNTRY_REPEATABLE_TECHS, STELLARIS_COUNTRY_RESEARCH,
+            STELLARIS_COUNTRY_RESEARCH_ALTERNATIVES, STELLARIS_COUNTRY_RULER,
+            STELLARIS_COUNTRY_STARBASES, STELLARIS_COUNTRY_STARBASE_BUILDINGS,
+            STELLARIS_COUNTRY_STARBASE_MODULES, STELLARIS_COUNTRY_SURVEYED_SYSTEMS,
+            STELLARIS_COUNTRY_TECHS, STELLARIS_COUNTRY_TRADITION_INFO,
+            STELLARIS_COUNTRY_WAR_ALLIES, STELLARIS_COUNTRY_WAR_BATLLES,
+            STELLARIS_FEDERATION_MEMBER, STELLARIS_FEDERATION_MEMBERS,
+            STELLARIS_FEDERATION_PROGRESS, STELLARIS_FLEET_STATS,
+            STELLARIS_GALACTIC_COMMUNITY_MEMBER, STELLARIS_GALACTIC_COMMUNITY_VOTES,
+            STELLARIS_GALACTIC_RESOLUTIONS, STELLARIS_GAME_DATE, STELLARIS_GAME_DAYS,
+            STELLARIS_GAME_REQUIRED_DLCS, STELLARIS_GAME_TICK, STELLARIS_GAME_VERSION,
+            STELLARIS_LEADER_AGE, STELLARIS_LEADER_LEVEL, STELLARIS_MARKET_FLUCTUATION,
+            STELLARIS_MARKET_PRICE, STELLARIS_PLANET_BUILDINGS, STELLARIS_PLANET_DISTRICTS,
+            STELLARIS_PLANET_INFO, STELLARIS_PLANET_STATS, STELLARIS_SPECIES_POPS,
+            STELLARIS_SYSTEM_COORDINATES, STELLARIS_WAR_EXHAUSTION, STELLARIS_WAR_PARTICIPANTS,
         },
-        renderers::{render_name, transform_input_name},
+        game_files::{SHIP_SIZE_COMMAND_POINTS, STRATEGIC_RESOURCES, TECH_AREAS},
+        renderers::{render_name, transform_localized},
+    },
+    models::{
+        common::{Entries, LocalizedText},
+        country::Country,
+        diplomacy::Vote,
+        galaxy::{GalacticObject, Starbase},
+        game_date::GameDate,
+        gamestate_model::Gamestate,
+        leader::Leader,
+        planet::Pop,
+        war::{War, WarGoal},
     },
-    models::gamestate_model::Gamestate,
 };
 
-pub fn get_country_infos(gm: Gamestate, save: &str) {
+/// Fills the metrics of a save from its gamestate and game id.
+pub type Extract = Box<dyn Fn(&Gamestate, &str)>;
+
+/// An extractor and the top level sections of the gamestate it reads.
+pub struct Extractor {
+    pub sections: &'static [&'static str],
+    pub extract: Extract,
+}
+
+impl Extractor {
+    fn new(
+        sections: &'static [&'static str],
+        extract: impl Fn(&Gamestate, &str) + 'static,
+    ) -> Self {
+        Extractor {
+            sections,
+            extract: Box::new(extract),
+        }
+    }
+}
+
+/// The extractors enabled by the `[metrics]` config. Saves are read one
+/// section at a time, skipping the sections none of them subscribed to.
+pub fn extractors(metrics: &MetricsConfig) -> Vec<Extractor> {
+    let per_fleet = metrics.fleets;
+    let mut extractors = vec![
+        Extractor::new(
+            &["version", "date", "tick", "required_dlcs"],
+            get_game_infos,
+        ),
+        Extractor::new(&["country"], get_country_infos),
+        Extractor::new(
+            &[
+                "country",
+                "fleet",
+                "ships",
+                "ship_design",
+                "galactic_object",
+            ],
+            move |gm, save| get_fleets(gm, save, per_fleet),
+        ),
+        Extractor::new(&["megastructures", "country"], get_megastructures),
+        Extractor::new(&["war", "country", "galactic_object"], get_wars),
+        Extractor::new(&["pop", "country", "species_db"], get_pops),
+        Extractor::new(&["leaders", "country", "date"], get_leaders),
+        Extractor::new(&["federation", "country"], get_federations),
+        Extractor::new(
+            &["galactic_community", "resolution", "country"],
+            get_galactic_community,
+        ),
+        Extractor::new(&["market", "country"], get_market),
+        Extractor::new(
+            &[
+                "starbase_mgr",
+                "ships",
+                "fleet",
+                "galactic_object",
+                "country",
+            ],
+            get_starbases,
+        ),
+    ];
+    if metrics.planets {
+        extractors.push(Extractor::new(
+            &["planets", "buildings", "country"],
+            get_planets,
+        ));
+    }
+    extractors
+}
+
+pub fn get_game_infos(gm: &Gamestate, save: &str) {
+    info!("Collecting Game Infos");
+
+    if let Some(Ok(date)) = gm.date.as_deref().map(|v| v.parse::<GameDate>()) {
+        STELLARIS_GAME_DAYS
+            .with_label_values(&[save])
+            .set(date.days_since_start());
+        STELLARIS_GAME_DATE
+            .with_label_values(&[save, "year"])
+            .set(date.year);
+        STELLARIS_GAME_DATE
+            .with_label_values(&[save, "month"])
+            .set(date.month);
+        STELLARIS_GAME_DATE
+            .with_label_values(&[save, "day"])
+            .set(date.day);
+    }
+
+    if let Some(tick) = gm.tick {
+        STELLARIS_GAME_TICK.with_label_values(&[save]).set(tick);
+    }
+
+    if let Some(version) = gm.version.as_deref() {
+        STELLARIS_GAME_VERSION
+            .with_label_values(&[save, version])
+            .set(1);
+    }
+
+    for dlc in &gm.required_dlcs {
+        STELLARIS_GAME_REQUIRED_DLCS
+            .with_label_values(&[save, dlc])
+            .set(1);
+    }
+}
+
+pub fn get_country_infos(gm: &Gamestate, save: &str) {
     info!("Collecting Country Infos");
-    if let None = *gm.country {
-        error!("Gamestate has no Countries: {:?}", gm.country);
+    if gm.country.is_empty() {
+        warn!("Gamestate has no Countries");
         return;
     }
 
-    if let Value::Object(countries) = gm.country.clone().unwrap() {
-        debug!(
-            "Detected {} Countries to extract info from",
-            countries.len()
-        );
-        for (key, value) in countries {
-            trace!("Analysing current country: {}", key);
-            match value {
-                Value::Object(country) => {
-                    // let name_temp = get_country_name(&country).unwrap_or(format!("{:?}", key));
-                    // let name = name_temp.as_str();
-                    let rendered_name = match country.get("name").map(|v| transform_input_name(v)) {
-                        Some(res) => match res {
-                            Ok(s) => s,
-                            Err(_) => key.clone().to_string(),
-                        },
-                        None => key.to_string(),
-                    };
-                    let name = rendered_name.as_str();
-                    get_country_powers(&country, name, save);
-                    get_country_fleets(&country, name, save);
-                    get_country_balance(&country, name, save);
-                    get_country_victory_score_n_rank(&country, name, save);
-                    get_country_war_allies(&country, name, save);
-                    get_country_controlled_celestial_bodies(&country, name, save);
-                    get_country_colonized_planets(&country, name, save);
-                    get_country_surveyed_systems(&country, name, save);
-                    if let (
-                        Some(Value::Object(fleet)),
-                        Some(Value::Object(designs)),
-                        Some(Value::Object(ships)),
-                    ) = (
-                        *gm.fleet.clone(),
-                        *gm.ship_design.clone(),
-                        *gm.ships.clone(),
-                    ) {
-                        get_country_ships_types(&country, &fleet, &ships, &designs, name, save);
-                    }
-                }
-                _ => {}
-            }
-        }
+    debug!(
+        "Detected {} Countries to extract info from",
+        gm.country.len()
+    );
+    for (id, country) in gm.country.iter() {
+        trace!("Analysing current country: {}", id);
+        let rendered_name = render_text(country.name.as_ref()).unwrap_or_else(|| id.to_string());
+        let name = rendered_name.as_str();
+        get_country_powers(country, name, save);
+        get_country_fleets(country, name, save);
+        get_country_balance(country, name, save);
+        get_country_budget(country, name, save);
+        get_country_technology(country, name, save);
+        get_country_playstyle(country, name, save);
+        get_country_victory_score_n_rank(country, name, save);
+        get_country_war_allies(country, name, save);
+        get_country_controlled_celestial_bodies(country, name, save);
+        get_country_colonized_planets(country, name, save);
+        get_country_surveyed_systems(country, name, save);
     }
 }
 
-fn get_country_powers(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_powers(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country powers");
-    match country["military_power"].clone() {
-        Value::Number(n) => {
-            STELLARIS_COUNTRY_POWER
-                .with_label_values(&[save, "military".as_ref(), name])
-                .set(n.as_f64().unwrap_or(0.0));
-        }
-        _ => {}
-    }
-    match country["tech_power"].clone() {
-        Value::Number(n) => {
+    for (kind, power) in [
+        ("military", country.military_power),
+        ("tech", country.tech_power),
+        ("economic", country.economy_power),
+    ] {
+        if let Some(power) = power {
             STELLARIS_COUNTRY_POWER
-                .with_label_values(&[save, "tech".as_ref(), name])
-                .set(n.as_f64().unwrap_or(0.0));
+                .with_label_values(&[save, kind, name])
+                .set(power);
         }
-        _ => {}
-    }
-    match country["economy_power"].clone() {
-        Value::Number(n) => {
-            STELLARIS_COUNTRY_POWER
-                .with_label_values(&[save, "economic".as_ref(), name])
-                .set(n.as_f64().unwrap_or(0.0));
-        }
-        _ => {}
     }
 }
 
-fn get_country_fleets(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_fleets(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country fleets");
 
-    country
-        .get("fleets_manager")
-        .and_then(|f| f.as_object())
-        .and_then(|fleets_manager| fleets_manager.get("owned_fleets"))
-        .and_then(|v| v.as_array())
-        .map(|fleets| {
-            STELLARIS_COUNTRY_FLEETS
-                .with_label_values(&[save, name])
-                .set(fleets.len() as i64)
-        });
+    if let Some(fleets_manager) = &country.fleets_manager {
+        STELLARIS_COUNTRY_FLEETS
+            .with_label_values(&[save, name])
+            .set(fleets_manager.owned_fleets.len() as i64);
+    }
 }
 
-fn get_country_balance(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_balance(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country balance");
 
-    country
-        .get("budget")
-        .and_then(|v| v.as_object())
-        .and_then(|budget| budget.get("income_high_water_mark"))
-        .and_then(|v| v.as_object())
-        .and_then(|income| income.get("current"))
-        .and_then(|v| v.as_object())
-        .map(|current| {
-            for (key, value) in current {
-                if let Value::Number(n) = value {
-                    STELLARIS_COUNTRY_BALANCE
-                        .with_label_values(&[save, name, key.as_str()])
-                        .set(n.as_f64().unwrap_or(0.0));
+    let stockpile = country
+        .modules
+        .as_ref()
+        .and_then(|modules| modules.standard_economy_module.as_ref());
+    for (key, amount) in stockpile.into_iter().flat_map(|module| &module.resources) {
+        STELLARIS_COUNTRY_BALANCE
+            .with_label_values(&[save, name, key])
+            .set(*amount);
+    }
+}
+
+fn get_country_budget(country: &Country, name: &str, save: &str) {
+    trace!("\tSeparating country budget");
+
+    let Some(budget) = &country.budget else {
+        return;
+    };
+    for (month, ledger) in [
+        ("current", &budget.current_month),
+        ("last", &budget.last_month),
+    ] {
+        let Some(ledger) = ledger else {
+            continue;
+        };
+        let mut net: HashMap<&str, f64> = HashMap::new();
+        for (direction, sources, sign) in [
+            ("income", &ledger.income, 1.0),
+            ("expense", &ledger.expenses, -1.0),
+        ] {
+            for (source, resources) in sources {
+                for (resource, amount) in resources {
+                    STELLARIS_COUNTRY_BUDGET
+                        .with_label_values(&[save, name, month, direction, source, resource])
+                        .set(*amount);
+                    *net.entry(resource).or_default() += sign * amount;
                 }
             }
-        });
+        }
+        for (resource, amount) in net {
+            STELLARIS_COUNTRY_NET_BALANCE
+                .with_label_values(&[save, name, month, resource])
+                .set(amount);
+        }
+    }
+}
+
+const TECH_AREAS_ORDER: [&str; 3] = ["physics", "society", "engineering"];
+
+fn get_country_technology(country: &Country, name: &str, save: &str) {
+    trace!("\tSeparating country technology");
+
+    let Some(status) = &country.tech_status else {
+        return;
+    };
+    let mut cache = RenderCache::default();
+
+    let mut researched: HashMap<&str, i64> = TECH_AREAS_ORDER.iter().map(|a| (*a, 0)).collect();
+    for (index, tech) in status.technology.iter().enumerate() {
+        let area = TECH_AREAS
+            .get(tech)
+            .map(|a| a.as_str())
+            .unwrap_or("unknown");
+        *researched.entry(area).or_default() += 1;
+        if tech.starts_with("tech_repeatable_") {
+            let level = status.level.get(index).copied().unwrap_or(1);
+            STELLARIS_COUNTRY_REPEATABLE_TECHS
+                .with_label_values(&[save, name, &cache.render(tech.to_string())])
+                .set(level);
+        }
+    }
+    for (area, count) in researched {
+        STELLARIS_COUNTRY_TECHS
+            .with_label_values(&[save, name, area])
+            .set(count);
+    }
+
+    for area in TECH_AREAS_ORDER {
+        let current = status.queue(area).first();
+        if let Some((tech, progress)) =
+            current.and_then(|research| Some((research.technology.as_ref()?, research.progress)))
+        {
+            STELLARIS_COUNTRY_RESEARCH
+                .with_label_values(&[save, name, area, &cache.render(tech.to_string())])
+                .set(progress.unwrap_or(0.0));
+        }
+
+        let alternatives = status.alternatives.as_ref().and_then(|a| a.area(area));
+        for tech in alternatives.into_iter().flat_map(|techs| techs.iter()) {
+            STELLARIS_COUNTRY_RESEARCH_ALTERNATIVES
+                .with_label_values(&[save, name, area, &cache.render(tech.to_string())])
+                .set(1);
+        }
+    }
 }
 
-fn get_country_victory_score_n_rank(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_playstyle(country: &Country, name: &str, save: &str) {
+    trace!("\tSeparating country ethics, government, traditions and perks");
+    let mut cache = RenderCache::default();
+
+    for ethic in country.ethos.iter().flat_map(|ethos| &ethos.ethic) {
+        STELLARIS_COUNTRY_ETHIC_INFO
+            .with_label_values(&[save, name, &cache.render(ethic.to_string())])
+            .set(1);
+    }
+
+    if let Some(government) = &country.government {
+        let mut render = |key: &Option<String>| {
+            key.as_ref()
+                .map(|key| cache.render(key.to_string()))
+                .unwrap_or_default()
+        };
+        let labels = [
+            render(&government.kind),
+            render(&government.authority),
+            render(&government.origin),
+        ];
+        STELLARIS_COUNTRY_GOVERNMENT_INFO
+            .with_label_values(&[save, name, &labels[0], &labels[1], &labels[2]])
+            .set(1);
+        for civic in government.civics.iter() {
+            STELLARIS_COUNTRY_CIVIC_INFO
+                .with_label_values(&[save, name, &cache.render(civic.to_string())])
+                .set(1);
+        }
+    }
+
+    for tradition in country.traditions.iter() {
+        STELLARIS_COUNTRY_TRADITION_INFO
+            .with_label_values(&[save, name, &cache.render(tradition.to_string())])
+            .set(1);
+    }
+    for perk in country.ascension_perks.iter() {
+        STELLARIS_COUNTRY_ASCENSION_PERK_INFO
+            .with_label_values(&[save, name, &cache.render(perk.to_string())])
+            .set(1);
+    }
+}
+
+fn get_country_victory_score_n_rank(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country victory rank and score");
-    country
-        .get("victory_rank")
-        .and_then(|rank| rank.as_i64())
-        .map(|value| {
-            STELLARIS_COUNTRY_VICTORY_STATUS
-                .with_label_values(&[save, name, "rank"])
-                .set(value as f64)
-        });
+    if let Some(rank) = country.victory_rank {
+        STELLARIS_COUNTRY_VICTORY_STATUS
+            .with_label_values(&[save, name, "rank"])
+            .set(rank as f64);
+    }
 
-    country
-        .get("victory_score")
-        .and_then(|rank| rank.as_i64())
-        .map(|value| {
-            STELLARIS_COUNTRY_VICTORY_STATUS
-                .with_label_values(&[save, name, "score"])
-                .set(value as f64)
-        });
+    if let Some(score) = country.victory_score {
+        STELLARIS_COUNTRY_VICTORY_STATUS
+            .with_label_values(&[save, name, "score"])
+            .set(score);
+    }
 }
 
-fn get_country_war_allies(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_war_allies(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country War Allies");
-    country
-        .get("war_allies")
-        .and_then(|v| v.as_array())
-        .map(|v| {
-            STELLARIS_COUNTRY_WAR_ALLIES
-                .with_label_values(&[save, name])
-                .set(v.len() as i64);
-        });
+    if let Some(war_allies) = &country.war_allies {
+        STELLARIS_COUNTRY_WAR_ALLIES
+            .with_label_values(&[save, name])
+            .set(war_allies.len() as i64);
+    }
 }
 
-fn get_country_controlled_celestial_bodies(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_controlled_celestial_bodies(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country Controlled Celestial Bodies");
-
-    country
-        .get("controlled_planets")
-        .and_then(|planets| planets.as_array())
-        .map(|planets| planets.len())
-        .map(|length| {
-            STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES
-                .with_label_values(&[save, name])
-                .set(length as i64)
-        });
+    if let Some(controlled_planets) = &country.controlled_planets {
+        STELLARIS_COUNTRY_CONTROLLED_CELESTIAL_BODIES
+            .with_label_values(&[save, name])
+            .set(controlled_planets.len() as i64);
+    }
 }
 
-fn get_country_colonized_planets(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_colonized_planets(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country Colonized Planets");
-    country
-        .get("owned_planets")
-        .and_then(|v| v.as_array())
-        .map(|planets| planets.len())
-        .map(|length| {
-            STELLARIS_COUNTRY_COLONIZED_PLANETS
-                .with_label_values(&[save, name])
-                .set(length as i64)
-        });
+    if let Some(owned_planets) = &country.owned_planets {
+        STELLARIS_COUNTRY_COLONIZED_PLANETS
+            .with_label_values(&[save, name])
+            .set(owned_planets.len() as i64);
+    }
 }
 
-fn get_country_surveyed_systems(country: &Map<String, Value>, name: &str, save: &str) {
+fn get_country_surveyed_systems(country: &Country, name: &str, save: &str) {
     trace!("\tSeparating country Surveyed Systems");
-    country
-        .get("surveyed")
-        .and_then(|v| v.as_array())
-        .map(|surveyed| surveyed.len())
-        .map(|len| {
-            STELLARIS_COUNTRY_SURVEYED_SYSTEMS
-                .with_label_values(&[save, name])
-                .set(len as i64)
-        });
+    if let Some(surveyed) = &country.surveyed {
+        STELLARIS_COUNTRY_SURVEYED_SYSTEMS
+            .with_label_values(&[save, name])
+            .set(surveyed.len() as i64);
+    }
 }
 
-fn get_country_ships_types(
-    country: &Map<String, Value>,
-    fleets: &Map<String, Value>,
-    ships: &Map<String, Value>,
-    ship_designs: &Map<String, Value>,
+pub fn get_fleets(gm: &Gamestate, save: &str, per_fleet: bool) {
+    info!("Collecting fleets info");
+    let ship_size = |ship: i64| {
+        let design = gm.ships.get(ship)?.ship_design?;
+        gm.ship_design.get(design)?.ship_size.as_deref()
+    };
+
+    let names = country_names(gm);
+    let mut systems = SystemNames::new(gm);
+    for (id, country) in gm.country.iter() {
+        let Some(manager) = &country.fleets_manager else {
+            continue;
+        };
+        let country = country_name(&names, id);
+
+        let mut sizes: HashMap<(&str, &str), i64> = HashMap::new();
+        let mut power: HashMap<&str, f64> = HashMap::new();
+        for fleet_id in manager.owned_fleets.iter().filter_map(|o| o.fleet) {
+            let Some(fleet) = gm.fleet.get(fleet_id) else {
+                continue;
+            };
+            let kind = fleet.fleet_type();
+            let military_power = fleet.military_power.unwrap_or(0.0);
+            *power.entry(kind).or_default() += military_power;
+
+            let mut fleet_command_points = 0.0;
+            for ship in &fleet.ships {
+                let size = ship_size(*ship).unwrap_or("no_size");
+                *sizes.entry((size, kind)).or_default() += 1;
+                fleet_command_points += SHIP_SIZE_COMMAND_POINTS.get(size).unwrap_or(&0.0);
+            }
+
+            if per_fleet {
+                let fleet_id = fleet_id.to_string();
+                let fleet_name =
+                    render_text(fleet.name.as_ref()).unwrap_or_else(|| fleet_id.clone());
+                let system = fleet
+                    .movement_manager
+                    .as_ref()
+                    .and_then(|m| m.coordinate.as_ref())
+                    .and_then(|c| c.origin)
+                    .map(|system| systems.name(system))
+                    .unwrap_or_default();
+                for (stat, value) in [
+                    ("military_power", military_power),
+                    ("ships", fleet.ships.len() as f64),
+                    ("command_points", fleet_command_points),
+                ] {
+                    STELLARIS_FLEET_STATS
+                        .with_label_values(&[
+                            save,
+                            &country,
+                            &fleet_id,
+                            &fleet_name,
+                            kind,
+                            &system,
+                            stat,
+                        ])
+                        .set(value);
+                }
+            }
+        }
+
+        for ((size, kind), count) in &sizes {
+            STELLARIS_COUNTRY_SHIP_SIZES
+                .with_label_values(&[save, &country, size, kind])
+                .set(*count as f64);
+        }
+        for (kind, power) in &power {
+            STELLARIS_COUNTRY_FLEET_POWER
+                .with_label_values(&[save, &country, kind])
+                .set(*power);
+        }
+    }
+}
+
+pub fn get_wars(gm: &Gamestate, save: &str) {
+    info!("collecting battles infos");
+
+    let names = country_names(gm);
+    let mut systems = SystemNames::new(gm);
+    for (id, war) in gm.war.iter() {
+        let id = id.to_string();
+        let name = render_text(war.name.as_ref()).unwrap_or_else(|| id.clone());
+
+        get_war_exhaustion(war, &id, &name, save);
+        let participants = get_war_participants(war, &id, &name, save, &names);
+        get_war_battles(war, &id, save, &names, &mut systems);
+
+        let war_goal = |goal: &Option<WarGoal>| {
+            let kind = goal.as_ref()?.kind.as_ref()?;
+            render_name(format!("war_goal_{}", kind)).ok()
+        };
+        if let (
+            Some(main_attacker),
+            Some(main_defender),
+            Some(att_war_goal),
+            Some(def_war_goal),
+            Some(date),
+        ) = (
+            participants.main_attacker,
+            participants.main_defender,
+            war_goal(&war.attacker_war_goal),
+            war_goal(&war.defender_war_goal),
+            war.start_date.as_deref(),
+        ) {
+            STELLARIS_COUNTRY_WAR_BATLLES
+                .with_label_values(&[
+                    save,
+                    main_attacker.as_str(),
+                    main_defender.as_str(),
+                    att_war_goal.as_str(),
+                    def_war_goal.as_str(),
+                    name.as_str(),
+                    date,
+                    id.as_str(),
+                ])
+                .set(war.battles.len() as i64)
+        }
+    }
+}
+
+fn get_war_exhaustion(war: &War, id: &str, name: &str, save: &str) {
+    for (side, exhaustion) in [
+        ("attacker", war.attacker_war_exhaustion),
+        ("defender", war.defender_war_exhaustion),
+    ] {
+        if let Some(exhaustion) = exhaustion {
+            STELLARIS_WAR_EXHAUSTION
+                .with_label_values(&[save, id, name, side])
+                .set(exhaustion);
+        }
+    }
+}
+
+/// Primary attacker and defender of a war, by rendered name.
+struct WarParticipants {
+    main_attacker: Option<String>,
+    main_defender: Option<String>,
+}
+
+fn get_war_participants(
+    war: &War,
+    id: &str,
     name: &str,
     save: &str,
+    names: &HashMap<i64, String>,
+) -> WarParticipants {
+    let mut participants = WarParticipants {
+        main_attacker: None,
+        main_defender: None,
+    };
+    for (side, members) in [("attacker", &war.attackers), ("defender", &war.defenders)] {
+        for member in members {
+            let Some(country) = member.country else {
+                continue;
+            };
+            let country = country_name(names, country);
+            let call_type = member.call_type.as_deref().unwrap_or("unknown");
+            STELLARIS_WAR_PARTICIPANTS
+                .with_label_values(&[save, id, name, side, &country, call_type])
+                .set(1);
+
+            let main = match side {
+                "attacker" => &mut participants.main_attacker,
+                _ => &mut participants.main_defender,
+            };
+            if call_type == "primary" && main.is_none() {
+                *main = Some(country);
+            }
+        }
+    }
+    participants
+}
+
+fn get_war_battles(
+    war: &War,
+    war_id: &str,
+    save: &str,
+    names: &HashMap<i64, String>,
+    systems: &mut SystemNames,
 ) {
-    trace!("\tSeparating country Ship Types");
-    let mut ships_ids: Vec<Value> = Vec::new();
-    country
-        .get("fleets_manager")
-        .and_then(|f| f.as_object())
-        .and_then(|fleets_manager| fleets_manager.get("owned_fleets"))
-        .and_then(|v| v.as_array())
-        .map(|owned_fleets| {
-            for owned in owned_fleets {
-                if let Some(fleet) = owned.get("fleet").and_then(|v| v.as_i64()) {
-                    if let Some(sps) = fleets
-                        .get(fleet.to_string().as_str())
-                        .and_then(|flt| flt.get("ships"))
-                        .and_then(|v| v.as_array())
-                    {
-                        ships_ids.extend_from_slice(sps);
-                    }
-                }
+    for (index, battle) in war.battles.iter().enumerate() {
+        let side = |ids: &[i64]| {
+            ids.iter()
+                .map(|id| country_name(names, *id))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let attacker = side(&battle.attackers);
+        let defender = side(&battle.defenders);
+        let battle_type = battle.kind.as_deref().unwrap_or("unknown");
+        let system = battle
+            .system
+            .map(|system| systems.name(system))
+            .unwrap_or_default();
+        let victor = match battle.attacker_victory {
+            Some(true) => attacker.as_str(),
+            Some(false) => defender.as_str(),
+            None => "",
+        };
+        let index = index.to_string();
+
+        for (loss, losses) in [
+            ("attacker", battle.attacker_losses),
+            ("defender", battle.defender_losses),
+        ] {
+            if let Some(losses) = losses {
+                STELLARIS_COUNTRY_BATTLE_LOSSES
+                    .with_label_values(&[
+                        save,
+                        &attacker,
+                        &defender,
+                        battle_type,
+                        loss,
+                        &index,
+                        &system,
+                        victor,
+                        war_id,
+                    ])
+                    .set(losses);
             }
-        });
+        }
+    }
+}
 
-    for ship_id in &ships_ids {
-        let id = ship_id.as_i64().unwrap_or(0);
-        let design = ships
-            .get(id.to_string().as_str())
-            .and_then(|v| v.get("ship_design"))
-            .and_then(|v| v.as_i64())
-            .unwrap_or(-1);
-        let size = ship_designs
-            .get(design.to_string().as_str())
-            .and_then(|v| v.as_object())
-            .and_then(|v| v.get("ship_size"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("no_size");
-        let sizes: HashMap<String, Value> = ships
-            .iter()
-            .filter(|(k, v)| {
-                ships_ids.contains(&json!(k.parse::<i64>().unwrap_or(-1)))
-                    && v.get("ship_design").and_then(|v| v.as_i64()).unwrap_or(-1) == design
+/// Rendered names of galactic objects, resolved on first use.
+struct SystemNames<'a> {
+    systems: &'a Entries<GalacticObject>,
+    rendered: HashMap<i64, String>,
+}
+
+impl<'a> SystemNames<'a> {
+    fn new(gm: &'a Gamestate) -> Self {
+        SystemNames {
+            systems: &gm.galactic_object,
+            rendered: HashMap::new(),
+        }
+    }
+
+    fn name(&mut self, id: i64) -> String {
+        let systems = self.systems;
+        self.rendered
+            .entry(id)
+            .or_insert_with(|| {
+                systems
+                    .get(id)
+                    .and_then(|system| render_text(system.name.as_ref()))
+                    .unwrap_or_else(|| id.to_string())
             })
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-        STELLARIS_COUNTRY_SHIP_SIZES
-            .with_label_values(&[save, name, size])
-            .set(sizes.len() as f64);
+            .clone()
     }
 }
 
-fn get_country_by_id(gm: Gamestate, id: &i64) -> Option<Value> {
-    if let Some(countries) = *gm.country.clone() {
-        match countries {
-            Value::Object(country) => {
-                if let Some(c) = country.get(&id.to_string()) {
-                    return Some(c.to_owned());
-                }
-            }
-            _ => {
-                return None;
-            }
+pub fn get_megastructures(gm: &Gamestate, save: &str) {
+    info!("Collecting megastructures info");
+    debug!(
+        "Detected {:?} megastructures to collect info from",
+        gm.megastructures.len(),
+    );
+
+    let names = country_names(gm);
+    let mut cache = RenderCache::default();
+    let mut structures: HashMap<(String, String), f64> = HashMap::new();
+    for structure in gm.megastructures.values() {
+        let name = structure
+            .kind
+            .as_ref()
+            .map(|kind| cache.render(kind.to_string()))
+            .unwrap_or_default();
+        let owner = country_name(&names, structure.owner.unwrap_or(-1));
+        *structures.entry((name, owner)).or_default() += 1.0;
+    }
+    for ((name, owner), count) in &structures {
+        STELLARIS_MEGASTRUCTURES
+            .with_label_values(&[save, name, owner])
+            .set(*count);
+    }
+}
+
+/// Renders a name written in the save, `None` when there is none.
+fn render_text(name: Option<&LocalizedText>) -> Option<String> {
+    name.and_then(|name| transform_localized(name).ok())
+}
+
+/// Rendered name of every country, keyed by country id.
+fn country_names(gm: &Gamestate) -> HashMap<i64, String> {
+    gm.country
+        .iter()
+        .filter_map(|(id, country)| {
+            let name = country.name.as_ref()?;
+            let rendered = transform_localized(name).unwrap_or_else(|_| id.to_string());
+            Some((id, rendered))
+        })
+        .collect()
+}
+
+fn country_name(names: &HashMap<i64, String>, id: i64) -> String {
+    names.get(&id).cloned().unwrap_or_else(|| id.to_string())
+}
+
+/// Owner country id of every owned planet, from the countries' `owned_planets`.
+fn planet_owners(gm: &Gamestate) -> HashMap<i64, i64> {
+    let mut owners = HashMap::new();
+    for (id, country) in gm.country.iter() {
+        for planet in country.owned_planets.iter().flatten() {
+            owners.insert(*planet, id);
         }
     }
-    return None;
+    owners
 }
 
-fn get_country_name(country: &Value) -> Option<String> {
-    if let Some(name) = country.get("name") {
-        if let Ok(country_name) = transform_input_name(name) {
-            return Some(country_name);
+/// Renders localisation keys once per extraction instead of once per entity.
+#[derive(Default)]
+struct RenderCache {
+    rendered: HashMap<String, String>,
+}
+
+impl RenderCache {
+    fn render(&mut self, key: String) -> String {
+        if let Some(rendered) = self.rendered.get(&key) {
+            return rendered.clone();
         }
+        let rendered = render_name(key.clone()).unwrap_or_else(|_| key.clone());
+        self.rendered.insert(key, rendered.clone());
+        rendered
     }
-    return None;
 }
 
-pub fn get_wars(gm: Gamestate, save: &str) {
-    //
-    info!("collecting battles infos");
-    if let None = *gm.war {
-        return;
+fn happiness_bucket(pop: &Pop) -> &'static str {
+    match pop.happiness {
+        None => "none",
+        Some(h) if h < 0.25 => "0-25%",
+        Some(h) if h < 0.5 => "25-50%",
+        Some(h) if h < 0.75 => "50-75%",
+        Some(_) => "75-100%",
     }
+}
 
-    let Some(wars) = gm.war.clone().and_then(|v| v.as_object().cloned()) else {
-        return;
-    };
-    for (id, war) in wars {
-        let name = war.get("name").map(|v| transform_input_name(v));
-        let start_date = war.get("start_date").and_then(|v| v.as_str().to_owned());
-
-        let attacker_war_exhaustion = war
-            .get("attacker_war_exhaustion")
-            .and_then(|v| v.as_str().to_owned());
-        let defender_war_exhaustion = war
-            .get("defender_war_exhaustion")
-            .and_then(|v| v.as_str().to_owned());
-
-        let attackers = war.get("attackers").and_then(|v| v.as_array().to_owned());
-        let defenders = war.get("defenders").and_then(|v| v.as_array().to_owned());
-        let main_attacker_id: Option<i64> = attackers
-            .map(|vec| {
-                vec.iter().filter(|&attacker| {
-                    if let serde_json::Value::Object(map) = attacker {
-                        map.get("call_type")
-                            .map_or(false, |call_type| call_type == "primary")
-                    } else {
-                        false
-                    }
-                })
-            })
-            .and_then(|mut filter_iter| filter_iter.next())
-            .and_then(|v| v.get("country"))
-            .and_then(|v| v.as_i64());
-
-        let main_defender_id: Option<i64> = defenders
-            .map(|vec| {
-                vec.iter().filter(|&defender| {
-                    if let serde_json::Value::Object(map) = defender {
-                        map.get("call_type")
-                            .map_or(false, |call_type| call_type == "primary")
-                    } else {
-                        false
-                    }
-                })
+pub fn get_pops(gm: &Gamestate, save: &str) {
+    info!("Collecting pops info");
+    debug!("Detected {} pops to collect info from", gm.pop.len());
+
+    let names = country_names(gm);
+    let owners = planet_owners(gm);
+    let mut cache = RenderCache::default();
+    let species_names: HashMap<i64, String> = gm
+        .species_db
+        .iter()
+        .map(|(id, species)| {
+            let name = species
+                .name
+                .as_ref()
+                .map(|name| cache.render(name.key.clone()))
+                .unwrap_or_else(|| id.to_string());
+            (id, name)
+        })
+        .collect();
+
+    let mut country_pops: HashMap<[String; 6], i64> = HashMap::new();
+    let mut species_pops: HashMap<String, i64> = HashMap::new();
+    for pop in gm.pop.values() {
+        let species = pop
+            .species
+            .map(|id| {
+                species_names
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| id.to_string())
             })
-            .and_then(|mut filter_iter| filter_iter.next())
-            .and_then(|v| v.get("country"))
-            .and_then(|v| v.as_i64());
-
-        let attacker_war_goal = war
-            .get("attacker_war_goal")
-            .and_then(|v| v.get("type"))
-            .and_then(|v| v.as_str())
-            .map(|v| render_name(format!("war_goal_{}", v)));
-
-        let defender_war_goal = war
-            .get("defender_war_goal")
-            .and_then(|v| v.get("type"))
-            .and_then(|v| v.as_str())
-            .map(|v| render_name(format!("war_goal_{}", v)));
+            .unwrap_or_else(|| "unknown".to_string());
+        *species_pops.entry(species.clone()).or_default() += 1;
 
-        if let (
-            Some(m_attacker_id),
-            Some(m_defender_id),
-            Some(Ok(att_war_goal)),
-            Some(Ok(def_war_goal)),
-            Some(Ok(war_name)),
-            Some(date),
-        ) = (
-            main_attacker_id,
-            main_defender_id,
-            attacker_war_goal,
-            defender_war_goal,
-            name,
-            start_date,
-        ) {
-            if let (Some(main_attacker), Some(main_defender)) = (
-                get_country_by_id(gm.clone(), &m_attacker_id),
-                get_country_by_id(gm.clone(), &m_defender_id),
-            ) {
-                let main_attacker_name = get_country_name(&main_attacker);
-                let main_defender_name = get_country_name(&main_defender);
+        let Some(owner) = pop.planet.and_then(|planet| owners.get(&planet)) else {
+            continue;
+        };
+        let country = country_name(&names, *owner);
+        let job = pop
+            .job
+            .as_ref()
+            .map(|j| cache.render(format!("job_{}", j)))
+            .unwrap_or_else(|| "unemployed".to_string());
+        let stratum = pop
+            .category
+            .as_ref()
+            .map(|c| cache.render(format!("pop_cat_{}", c)))
+            .unwrap_or_else(|| "none".to_string());
+        let ethic = pop
+            .ethos
+            .as_ref()
+            .and_then(|ethos| ethos.ethic.first())
+            .map(|e| cache.render(e.to_string()))
+            .unwrap_or_else(|| "none".to_string());
+        let happiness = happiness_bucket(pop).to_string();
 
-                let number_of_battles = war
-                    .get("battles")
-                    .and_then(|v| v.as_array())
-                    .map(|v| v.len() as i64);
+        *country_pops
+            .entry([country, species, job, stratum, ethic, happiness])
+            .or_default() += 1;
+    }
 
-                let battles = war.get("battles").and_then(|v| v.as_array());
+    for ([country, species, job, stratum, ethic, happiness], count) in &country_pops {
+        STELLARIS_COUNTRY_POPS
+            .with_label_values(&[save, country, species, job, stratum, ethic, happiness])
+            .set(*count);
+    }
+    for (species, count) in &species_pops {
+        STELLARIS_SPECIES_POPS
+            .with_label_values(&[save, species])
+            .set(*count);
+    }
+}
 
-                STELLARIS_COUNTRY_WAR_BATLLES
-                    .with_label_values(&[
-                        save,
-                        main_attacker_name
-                            .unwrap_or(m_attacker_id.to_string())
-                            .as_str(),
-                        main_defender_name
-                            .unwrap_or(m_defender_id.to_string())
-                            .as_str(),
-                        att_war_goal.as_str(),
-                        def_war_goal.as_str(),
-                        war_name.as_str(),
-                        date,
-                        id.as_str(),
-                    ])
-                    .set(number_of_battles.unwrap_or(0))
+pub fn get_planets(gm: &Gamestate, save: &str) {
+    info!("Collecting planets info");
+
+    let names = country_names(gm);
+    let mut cache = RenderCache::default();
+    let mut colonies = 0;
+    for (id, planet) in gm.planets.planet.iter() {
+        let Some(owner) = planet.owner else {
+            continue;
+        };
+        let country = country_name(&names, owner);
+        let id = id.to_string();
+        let name = render_text(planet.name.as_ref()).unwrap_or_else(|| id.clone());
+        colonies += 1;
+
+        let class = planet
+            .planet_class
+            .as_ref()
+            .map(|c| cache.render(c.to_string()))
+            .unwrap_or_default();
+        let designation = planet
+            .final_designation
+            .as_ref()
+            .or(planet.designation.as_ref())
+            .map(|d| cache.render(d.to_string()))
+            .unwrap_or_default();
+        STELLARIS_PLANET_INFO
+            .with_label_values(&[save, &country, &id, &name, &class, &designation])
+            .set(1);
+
+        for (stat, value) in planet.stats() {
+            if let Some(value) = value {
+                STELLARIS_PLANET_STATS
+                    .with_label_values(&[save, &country, &id, &name, stat])
+                    .set(value);
             }
         }
+
+        let mut districts: HashMap<String, i64> = HashMap::new();
+        for district in planet.districts() {
+            *districts
+                .entry(cache.render(district.to_string()))
+                .or_default() += 1;
+        }
+        for (district, count) in &districts {
+            STELLARIS_PLANET_DISTRICTS
+                .with_label_values(&[save, &country, &id, &name, district])
+                .set(*count);
+        }
+
+        let mut building_counts: HashMap<String, i64> = HashMap::new();
+        for building in &planet.buildings_cache {
+            if let Some(kind) = gm.buildings.get(*building).and_then(|b| b.kind.as_ref()) {
+                *building_counts
+                    .entry(cache.render(kind.to_string()))
+                    .or_default() += 1;
+            }
+        }
+        for (building, count) in &building_counts {
+            STELLARIS_PLANET_BUILDINGS
+                .with_label_values(&[save, &country, &id, &name, building])
+                .set(*count);
+        }
+    }
+    debug!("Collected {} colonized planets", colonies);
+}
+
+/// When the ruler of each country took office, per campaign. Saves do not
+/// record it, so a term starts with the first ingested save its ruler is seen
+/// in, and a new one whenever the `ruler` of the country changes.
+static RULER_TERMS: Lazy<Mutex<HashMap<String, RulerTerms>>> = Lazy::new(Default::default);
+
+#[derive(Debug, Default)]
+struct RulerTerms(HashMap<i64, RulerTerm>);
+
+#[derive(Debug, Clone, Copy)]
+struct RulerTerm {
+    leader: i64,
+    since: GameDate,
+    seen: GameDate,
+}
+
+impl RulerTerms {
+    /// Records `leader` ruling `country` on `date`, returning since when.
+    fn observe(&mut self, country: i64, leader: i64, date: GameDate) -> GameDate {
+        let term = self.0.entry(country).or_insert(RulerTerm {
+            leader,
+            since: date,
+            seen: date,
+        });
+        if term.leader == leader {
+            term.since = term.since.min(date);
+            term.seen = term.seen.max(date);
+        } else {
+            *term = RulerTerm {
+                leader,
+                since: date,
+                seen: date,
+            };
+        }
+        term.since
     }
 }
 
-fn get_war_battles(battles: &Vec<Value>) {}
+fn get_leader_name(leader: &Leader) -> Option<String> {
+    let name = leader.name.as_ref()?;
+    if let Some(full_names) = &name.full_names {
+        return transform_localized(full_names).ok();
+    }
+    // Saves from before 3.8 store the first and last name directly.
+    let parts: Vec<String> = [&name.first_name, &name.second_name]
+        .into_iter()
+        .flatten()
+        .filter_map(|part| transform_localized(part).ok())
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
 
-fn get_war_exhaustion() {}
+pub fn get_leaders(gm: &Gamestate, save: &str) {
+    info!("Collecting leaders info");
+    debug!("Detected {} leaders to collect info from", gm.leaders.len());
 
-fn get_country_name_by_id(gm: Gamestate, id: &str) -> Option<String> {
-    let name = gm
+    let names = country_names(gm);
+    let today = gm.date.as_deref().and_then(|d| d.parse::<GameDate>().ok());
+    let rulers: HashMap<i64, i64> = gm
         .country
-        .and_then(|countries| countries.get(id).cloned())
-        .and_then(|country| country.get("name").cloned())
-        .map(|v| transform_input_name(&v));
-
-    match name {
-        Some(nm) => match nm {
-            Ok(n) => Some(n),
-            Err(_) => None,
-        },
-        None => None,
+        .iter()
+        .filter_map(|(id, country)| Some((country.ruler?, id)))
+        .collect();
+
+    let mut cache = RenderCache::default();
+    let mut classes: HashMap<(String, String), i64> = HashMap::new();
+    let mut traits: HashMap<(String, String, String), i64> = HashMap::new();
+    let mut assignments: HashMap<(String, String, String), i64> = HashMap::new();
+    for (id, leader) in gm.leaders.iter() {
+        let Some(owner) = leader.country else {
+            continue;
+        };
+        let country = country_name(&names, owner);
+        let class = leader.class.as_deref().unwrap_or("unknown").to_string();
+        let leader_id = id.to_string();
+        let name = get_leader_name(leader).unwrap_or_else(|| leader_id.clone());
+        let labels = [save, &country, &leader_id, &name, &class];
+
+        *classes.entry((country.clone(), class.clone())).or_default() += 1;
+        if let Some(level) = leader.level {
+            STELLARIS_LEADER_LEVEL.with_label_values(&labels).set(level);
+        }
+        if let Some(age) = leader.age {
+            STELLARIS_LEADER_AGE.with_label_values(&labels).set(age);
+        }
+        for leader_trait in leader.traits() {
+            let leader_trait = cache.render(leader_trait.to_string());
+            *traits
+                .entry((country.clone(), class.clone(), leader_trait))
+                .or_default() += 1;
+        }
+        let assignment = leader
+            .location
+            .as_ref()
+            .and_then(|location| location.assignment.as_deref())
+            .unwrap_or("none")
+            .to_string();
+        *assignments
+            .entry((country.clone(), class.clone(), assignment))
+            .or_default() += 1;
+
+        if let (Some(ruled), Some(today)) = (rulers.get(&id), today) {
+            let since = RULER_TERMS
+                .lock()
+                .unwrap()
+                .entry(save.to_string())
+                .or_default()
+                .observe(*ruled, id, today);
+            STELLARIS_COUNTRY_RULER
+                .with_label_values(&labels)
+                .set(today.days_since_start() - since.days_since_start());
+        }
+    }
+
+    for ((country, class), count) in &classes {
+        STELLARIS_COUNTRY_LEADERS
+            .with_label_values(&[save, country, class])
+            .set(*count);
+    }
+    for ((country, class, leader_trait), count) in &traits {
+        STELLARIS_COUNTRY_LEADER_TRAITS
+            .with_label_values(&[save, country, class, leader_trait])
+            .set(*count);
+    }
+    for ((country, class, assignment), count) in &assignments {
+        STELLARIS_COUNTRY_LEADER_ASSIGNMENTS
+            .with_label_values(&[save, country, class, assignment])
+            .set(*count);
     }
 }
 
-pub fn get_megastructures(gm: Gamestate, save: &str) {
-    info!("Collecting megastructures info");
-    if let Some(structures) = *gm.megastructures.clone() {
-        match structures {
-            Value::Object(structs) => {
-                debug!(
-                    "Detected {:?} megastructures to collect info from",
-                    structs.len(),
-                );
-                for (_, mstruct) in structs.iter() {
-                    let mut name = String::new();
-                    let owner = mstruct.get("owner").and_then(|v| v.as_i64()).unwrap_or(-1);
-                    let owner_name = get_country_name_by_id(gm.clone(), owner.to_string().as_str());
-                    if let Some(Ok(nm)) = mstruct
-                        .get("type")
-                        .and_then(|v| v.as_str())
-                        .map(|v| render_name(v.to_string()))
-                    {
-                        name = nm;
-                    }
-
-                    let same_structres_owner: Map<String, Value> = structs
-                        .iter()
-                        .filter(|(_, v)| {
-                            v.get("type") == mstruct.get("type")
-                                && v.get("owner") == mstruct.get("owner")
-                        })
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                        .collect();
-                    STELLARIS_MEGASTRUCTURES
-                        .with_label_values(&[
-                            save,
-                            name.as_str(),
-                            owner_name.unwrap_or(owner.to_string()).as_str(),
-                        ])
-                        .set(same_structres_owner.len() as f64);
+pub fn get_federations(gm: &Gamestate, save: &str) {
+    info!("Collecting federations info");
+
+    let names = country_names(gm);
+    let mut cache = RenderCache::default();
+    for (id, federation) in gm.federation.iter() {
+        let name = render_text(federation.name.as_ref()).unwrap_or_else(|| id.to_string());
+        let progression = federation.federation_progression.as_ref();
+        let federation_type = progression
+            .and_then(|p| p.federation_type.as_ref())
+            .map(|t| cache.render(t.to_string()))
+            .unwrap_or_default();
+        let president = federation
+            .leader
+            .map(|leader| country_name(&names, leader))
+            .unwrap_or_default();
+
+        let members: Vec<String> = federation
+            .members
+            .iter()
+            .map(|member| country_name(&names, *member))
+            .collect();
+        STELLARIS_FEDERATION_MEMBERS
+            .with_label_values(&[save, &name, &federation_type, &president])
+            .set(members.len() as i64);
+        for member in &members {
+            STELLARIS_FEDERATION_MEMBER
+                .with_label_values(&[save, &name, member])
+                .set(1);
+        }
+
+        let Some(progression) = progression else {
+            continue;
+        };
+        for (stat, value) in [
+            ("level", progression.levels),
+            ("cohesion", progression.cohesion),
+            ("experience", progression.experience),
+        ] {
+            if let Some(value) = value {
+                STELLARIS_FEDERATION_PROGRESS
+                    .with_label_values(&[save, &name, stat])
+                    .set(value);
+            }
+        }
+    }
+}
+
+/// Sums the weight behind each option of the current vote, entries without
+/// weight count as one vote.
+fn tally_votes(votes: &[Vote]) -> HashMap<String, f64> {
+    let mut tally = HashMap::new();
+    for entry in votes {
+        let Some(vote) = &entry.vote else {
+            continue;
+        };
+        *tally.entry(vote.clone()).or_default() += entry.weight.unwrap_or(1.0);
+    }
+    tally
+}
+
+pub fn get_galactic_community(gm: &Gamestate, save: &str) {
+    info!("Collecting galactic community info");
+    let mut cache = RenderCache::default();
+
+    if let Some(community) = &gm.galactic_community {
+        let names = country_names(gm);
+        for member in &community.members {
+            let seat = if community.council.contains(member) {
+                "yes"
+            } else {
+                "no"
+            };
+            STELLARIS_GALACTIC_COMMUNITY_MEMBER
+                .with_label_values(&[save, &country_name(&names, *member), seat])
+                .set(1);
+        }
+
+        if let Some(resolution) = &community.voting {
+            let resolution = cache.render(resolution.to_string());
+            for (vote, weight) in &tally_votes(&community.votes.0) {
+                STELLARIS_GALACTIC_COMMUNITY_VOTES
+                    .with_label_values(&[save, &resolution, vote])
+                    .set(*weight);
+            }
+        }
+    }
+
+    let mut passed: HashMap<String, i64> = HashMap::new();
+    for kind in gm.resolution.0.iter().filter_map(|r| r.kind.as_ref()) {
+        *passed.entry(cache.render(kind.to_string())).or_default() += 1;
+    }
+    for (resolution, count) in &passed {
+        STELLARIS_GALACTIC_RESOLUTIONS
+            .with_label_values(&[save, resolution])
+            .set(*count);
+    }
+}
+
+fn market_price(resource: &str, fluctuation: f64) -> Option<f64> {
+    let base = STRATEGIC_RESOURCES.base_prices.get(resource)?;
+    Some(base * (1.0 + fluctuation / 100.0))
+}
+
+pub fn get_market(gm: &Gamestate, save: &str) {
+    info!("Collecting market info");
+    let Some(market) = &gm.market else {
+        return;
+    };
+
+    let names = country_names(gm);
+    let mut cache = RenderCache::default();
+    let keys = &STRATEGIC_RESOURCES.order;
+    let resources: Vec<String> = keys.iter().map(|r| cache.render(r.clone())).collect();
+
+    for (index, fluctuation) in market.fluctuations.iter().enumerate() {
+        let Some(key) = keys.get(index) else {
+            break;
+        };
+        let Some(price) = market_price(key, *fluctuation) else {
+            continue;
+        };
+        STELLARIS_MARKET_PRICE
+            .with_label_values(&[save, &resources[index]])
+            .set(price);
+        STELLARIS_MARKET_FLUCTUATION
+            .with_label_values(&[save, &resources[index]])
+            .set(*fluctuation);
+    }
+
+    let countries: Vec<String> = market
+        .id
+        .iter()
+        .map(|id| country_name(&names, *id))
+        .collect();
+    for (direction, rows) in [
+        ("bought", &market.resources_bought),
+        ("sold", &market.resources_sold),
+    ] {
+        for (row, country) in rows.0.iter().zip(&countries) {
+            for (index, amount) in row.iter().enumerate() {
+                if *amount == 0.0 || index >= keys.len() {
+                    continue;
                 }
+                STELLARIS_COUNTRY_MARKET_TRADE
+                    .with_label_values(&[save, country, &resources[index], direction])
+                    .set(*amount);
+            }
+        }
+    }
+
+    let Some(internal) = &market.internal_market_fluctuations else {
+        return;
+    };
+    for (id, row) in internal.country.iter().zip(&internal.resources) {
+        let country = country_name(&names, *id);
+        for (key, fluctuation) in row {
+            if let Some(price) = market_price(key, *fluctuation) {
+                STELLARIS_COUNTRY_MARKET_PRICE
+                    .with_label_values(&[save, &country, &cache.render(key.clone())])
+                    .set(price);
+            }
+        }
+    }
+}
+
+pub fn get_starbases(gm: &Gamestate, save: &str) {
+    info!("Collecting starbases and systems info");
+    let names = country_names(gm);
+    let mut cache = RenderCache::default();
+
+    // Older saves keep the owner on the starbase, newer ones only on the
+    // fleet of the station ship.
+    let owner_of = |starbase: &Starbase| -> Option<i64> {
+        if let Some(owner) = starbase.owner {
+            return Some(owner);
+        }
+        let fleet = gm.ships.get(starbase.station?)?.fleet?;
+        gm.fleet.get(fleet)?.owner
+    };
+
+    let mut owners: HashMap<i64, String> = HashMap::new();
+    let mut levels: HashMap<i64, String> = HashMap::new();
+    let mut counts: HashMap<(String, String), i64> = HashMap::new();
+    let mut modules: HashMap<(String, String), i64> = HashMap::new();
+    let mut buildings: HashMap<(String, String), i64> = HashMap::new();
+    for (id, starbase) in gm.starbase_mgr.starbases.iter() {
+        let Some(owner) = owner_of(starbase) else {
+            continue;
+        };
+        let country = country_name(&names, owner);
+        let level = starbase
+            .level
+            .as_ref()
+            .map(|l| cache.render(l.to_string()))
+            .unwrap_or_default();
+        *counts.entry((country.clone(), level.clone())).or_default() += 1;
+        for module in &starbase.modules.0 {
+            let module = cache.render(format!("sm_{}", module));
+            *modules.entry((country.clone(), module)).or_default() += 1;
+        }
+        for building in &starbase.buildings.0 {
+            let building = cache.render(format!("sm_{}", building));
+            *buildings.entry((country.clone(), building)).or_default() += 1;
+        }
+        owners.insert(id, country);
+        levels.insert(id, level);
+    }
+    for ((country, level), count) in &counts {
+        STELLARIS_COUNTRY_STARBASES
+            .with_label_values(&[save, country, level])
+            .set(*count);
+    }
+    for ((country, module), count) in &modules {
+        STELLARIS_COUNTRY_STARBASE_MODULES
+            .with_label_values(&[save, country, module])
+            .set(*count);
+    }
+    for ((country, building), count) in &buildings {
+        STELLARIS_COUNTRY_STARBASE_BUILDINGS
+            .with_label_values(&[save, country, building])
+            .set(*count);
+    }
+
+    let mut owned_systems: HashMap<&str, i64> = HashMap::new();
+    for (id, system) in gm.galactic_object.iter() {
+        let Some(coordinate) = &system.coordinate else {
+            continue;
+        };
+        let starbase = system.starbase();
+        let owner = starbase.and_then(|s| owners.get(&s));
+        let level = starbase.and_then(|s| levels.get(&s));
+        if let Some(owner) = owner {
+            *owned_systems.entry(owner).or_default() += 1;
+        }
+
+        let id = id.to_string();
+        let name = render_text(system.name.as_ref()).unwrap_or_else(|| id.clone());
+        let owner = owner.map(|o| o.as_str()).unwrap_or_default();
+        let level = level.map(|l| l.as_str()).unwrap_or_default();
+        for (axis, value) in [("x", coordinate.x), ("y", coordinate.y)] {
+            if let Some(value) = value {
+                STELLARIS_SYSTEM_COORDINATES
+                    .with_label_values(&[save, &id, &name, owner, level, axis])
+                    .set(value);
             }
-            _ => {}
         }
     }
+    for (country, count) in &owned_systems {
+        STELLARIS_COUNTRY_OWNED_SYSTEMS
+            .with_label_values(&[save, country])
+            .set(*count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ruler_terms() {
+        let date = |d: &str| d.parse::<GameDate>().unwrap();
+        let mut terms = RulerTerms::default();
+        assert_eq!(terms.observe(0, 1, date("2200.01.01")), date("2200.01.01"));
+        assert_eq!(terms.observe(0, 1, date("2210.01.01")), date("2200.01.01"));
+        assert_eq!(terms.observe(2, 7, date("2210.01.01")), date("2210.01.01"));
+
+        // A new ruler starts a new term.
+        assert_eq!(terms.observe(0, 5, date("2220.01.01")), date("2220.01.01"));
+        assert_eq!(terms.observe(0, 5, date("2230.01.01")), date("2220.01.01"));
+        // An older save of the same ruler moves the start back.
+        assert_eq!(terms.observe(0, 5, date("2215.01.01")), date("2215.01.01"));
+        assert_eq!(terms.observe(2, 7, date("2230.01.01")), date("2210.01.01"));
+    }
 }
@@ -0,0 +1,209 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use walkdir::WalkDir;
+
+use crate::{
+    exporter::configs::CONFIGS,
+    parser::{parse_file, Value},
+};
+
+/// Research area (`physics`, `society`, `engineering`) of every technology,
+/// read from `common/technology` under the configured `game_files_dir`.
+pub static TECH_AREAS: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let game_files_dir = CONFIGS.lock().unwrap().paths.game_files_dir.clone();
+    let dir = Path::new(&game_files_dir).join("common").join("technology");
+    let areas = load_tech_areas(&dir);
+    debug!("Loaded the research area of {} technologies", areas.len());
+    areas
+});
+
+/// Resources of `common/strategic_resources`, with their base market price.
+pub static STRATEGIC_RESOURCES: Lazy<StrategicResources> = Lazy::new(|| {
+    let game_files_dir = CONFIGS.lock().unwrap().paths.game_files_dir.clone();
+    let dir = Path::new(&game_files_dir)
+        .join("common")
+        .join("strategic_resources");
+    let resources = load_strategic_resources(&dir);
+    debug!(
+        "Loaded {} resources, {} of them tradable",
+        resources.order.len(),
+        resources.base_prices.len()
+    );
+    resources
+});
+
+/// Command points (`fleet_slot_size`) used by each ship size of
+/// `common/ship_sizes`.
+pub static SHIP_SIZE_COMMAND_POINTS: Lazy<HashMap<String, f64>> = Lazy::new(|| {
+    let game_files_dir = CONFIGS.lock().unwrap().paths.game_files_dir.clone();
+    let dir = Path::new(&game_files_dir).join("common").join("ship_sizes");
+    let sizes = load_ship_sizes(&dir);
+    debug!("Loaded the command points of {} ship sizes", sizes.len());
+    sizes
+});
+
+#[derive(Debug, Default)]
+pub struct StrategicResources {
+    /// Every resource in the order the game defines them, which is the order
+    /// of the market arrays of the save.
+    pub order: Vec<String>,
+    /// Price of a single unit of each tradable resource, before fluctuations.
+    pub base_prices: HashMap<String, f64>,
+}
+
+/// Parses the `.txt` files of `dir` in the order the game loads them, which
+/// is sorted by name, and hands each of them to `read`. Files that cannot be
+/// read or parsed are skipped with a warning.
+fn read_game_files(dir: &Path, mut read: impl FnMut(&Value)) {
+    let files = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "txt"));
+    for file in files {
+        let content = match fs::read_to_string(file.path()) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Could not read {:?}: {}", file.path(), e);
+                continue;
+            }
+        };
+        match parse_file(&content) {
+            Ok(parsed) => read(&parsed),
+            Err(e) => warn!("Skipping {:?}, it could not be parsed: {}", file.path(), e),
+        }
+    }
+}
+
+fn load_tech_areas(dir: &Path) -> HashMap<String, String> {
+    let mut areas = HashMap::new();
+    if !dir.is_dir() {
+        warn!(
+            "Technology folder {:?} not found, tech areas are unknown",
+            dir
+        );
+        return areas;
+    }
+
+    read_game_files(dir, |file| areas.extend(parse_tech_areas(file)));
+    areas
+}
+
+fn load_strategic_resources(dir: &Path) -> StrategicResources {
+    let mut resources = StrategicResources::default();
+    if !dir.is_dir() {
+        warn!(
+            "Strategic resources folder {:?} not found, market prices are unknown",
+            dir
+        );
+        return resources;
+    }
+
+    read_game_files(dir, |file| parse_strategic_resources(file, &mut resources));
+    resources
+}
+
+fn load_ship_sizes(dir: &Path) -> HashMap<String, f64> {
+    let mut sizes = HashMap::new();
+    if !dir.is_dir() {
+        warn!(
+            "Ship sizes folder {:?} not found, command points are unknown",
+            dir
+        );
+        return sizes;
+    }
+
+    read_game_files(dir, |file| sizes.extend(parse_ship_sizes(file)));
+    sizes
+}
+
+/// Reads the `area` of each top level `tech_name = { ... }` block.
+fn parse_tech_areas(file: &Value) -> HashMap<String, String> {
+    file.fields()
+        .filter_map(|tech| {
+            let area = tech.value.get("area")?.as_str()?;
+            Some((tech.key.as_str().to_string(), area.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the top level `resource = { ... }` blocks. The market sells
+/// `market_amount` units of a tradable resource for `market_price` energy.
+fn parse_strategic_resources(file: &Value, resources: &mut StrategicResources) {
+    for resource in file.fields().filter(|f| !f.key.as_str().starts_with('@')) {
+        let key = resource.key.as_str().to_string();
+        let amount = resource.value.get("market_amount").and_then(|v| v.as_f64());
+        let price = resource.value.get("market_price").and_then(|v| v.as_f64());
+        if let (Some(amount), Some(price)) = (amount, price) {
+            if amount > 0.0 {
+                resources.base_prices.insert(key.clone(), price / amount);
+            }
+        }
+        resources.order.push(key);
+    }
+}
+
+/// Reads the `fleet_slot_size` of each top level `size = { ... }` block.
+fn parse_ship_sizes(file: &Value) -> HashMap<String, f64> {
+    file.fields()
+        .filter_map(|size| {
+            let slots = size.value.get("fleet_slot_size")?.as_f64()?;
+            Some((size.key.as_str().to_string(), slots))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tech_areas() {
+        let content = "\u{feff}@tier1cost = 500\n\
+            tech_lasers_1 = { area = physics tier = 1 cost = @tier1cost }\n\
+            # a comment\n\
+            tech_colonization_1 = {\n\tcost = @[ tier1cost * 2 ]\n\tarea = society\n\
+            \tpotential = { years_passed > 20 NOT = { has_country_flag = no_tech } }\n}\n\
+            tech_broken = { tier = 2 }\n";
+        let areas = parse_tech_areas(&parse_file(content).unwrap());
+        assert_eq!(areas.len(), 2);
+        assert_eq!(areas["tech_lasers_1"], "physics");
+        assert_eq!(areas["tech_colonization_1"], "society");
+    }
+
+    #[test]
+    fn test_parse_strategic_resources() {
+        let content = "@price = 100\n\
+            time = { visibility = no }\n\
+            energy = { tradable = no max = 50000 }\n\
+            minerals = { tradable = yes market_amount = 100 market_price = 100 }\n\
+            alloys = {\n\tmarket_amount = 25\n\tmarket_price = 100\n\tai_weight = { weight = 2 }\n}\n";
+        let mut resources = StrategicResources::default();
+        parse_strategic_resources(&parse_file(content).unwrap(), &mut resources);
+        let content = "sr_zro = { market_amount = 5 market_price = 100 }\n";
+        parse_strategic_resources(&parse_file(content).unwrap(), &mut resources);
+
+        assert_eq!(
+            resources.order,
+            vec!["time", "energy", "minerals", "alloys", "sr_zro"]
+        );
+        assert_eq!(resources.base_prices.len(), 3);
+        assert_eq!(resources.base_prices["minerals"], 1.0);
+        assert_eq!(resources.base_prices["alloys"], 4.0);
+        assert_eq!(resources.base_prices["sr_zro"], 20.0);
+    }
+
+    #[test]
+    fn test_parse_ship_sizes() {
+        let content = "@corvette_cost = 30\n\
+            corvette = { max_speed = 160 fleet_slot_size = 1 }\n\
+            juggernaut = {\n\tfleet_slot_size = 24\n\tclass = shipclass_starbase\n}\n\
+            starbase_outpost = { max_hitpoints = 2000 }\n";
+        let sizes = parse_ship_sizes(&parse_file(content).unwrap());
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes["corvette"], 1.0);
+        assert_eq!(sizes["juggernaut"], 24.0);
+    }
+}
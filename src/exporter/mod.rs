@@ -1,4 +1,6 @@
 pub mod configs;
 pub mod exporter;
 pub mod extractor;
+pub mod game_files;
+pub mod openmetrics;
 pub mod renderers;
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, io::Write};
+
+use prometheus::proto::{MetricFamily, MetricType};
+
+/// The series gathered for one save, stamped with a timestamp in seconds.
+pub struct Snapshot {
+    pub timestamp: i64,
+    pub families: Vec<MetricFamily>,
+}
+
+struct FamilyLines {
+    help: String,
+    kind: &'static str,
+    samples: Vec<String>,
+}
+
+/// Writes the snapshots in the OpenMetrics text format understood by
+/// `promtool tsdb create-blocks-from openmetrics`.
+///
+/// Snapshots must be sorted by timestamp. Every family is written once, with
+/// the samples of all the snapshots grouped under it. Returns the number of
+/// samples written.
+pub fn write_openmetrics<W: Write>(out: &mut W, snapshots: &[Snapshot]) -> std::io::Result<usize> {
+    let mut families: BTreeMap<String, FamilyLines> = BTreeMap::new();
+    for snapshot in snapshots {
+        for family in &snapshot.families {
+            let lines = families
+                .entry(family.get_name().to_string())
+                .or_insert_with(|| FamilyLines {
+                    help: family.get_help().to_string(),
+                    kind: match family.get_field_type() {
+                        MetricType::GAUGE => "gauge",
+                        _ => "unknown",
+                    },
+                    samples: Vec::new(),
+                });
+            for metric in family.get_metric() {
+                let value = match family.get_field_type() {
+                    MetricType::GAUGE => metric.get_gauge().get_value(),
+                    MetricType::UNTYPED => metric.get_untyped().get_value(),
+                    _ => continue,
+                };
+                let labels = metric
+                    .get_label()
+                    .iter()
+                    .map(|l| format!("{}=\"{}\"", l.get_name(), escape(l.get_value())))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                lines.samples.push(format!(
+                    "{}{{{}}} {} {}",
+                    family.get_name(),
+                    labels,
+                    format_value(value),
+                    snapshot.timestamp
+                ));
+            }
+        }
+    }
+
+    let mut written = 0;
+    for (name, lines) in families {
+        writeln!(out, "# HELP {} {}", name, escape(&lines.help))?;
+        writeln!(out, "# TYPE {} {}", name, lines.kind)?;
+        for sample in &lines.samples {
+            writeln!(out, "{}", sample)?;
+        }
+        written += lines.samples.len();
+    }
+    writeln!(out, "# EOF")?;
+    Ok(written)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{core::Collector, GaugeVec, Opts};
+
+    fn snapshot(timestamp: i64, value: f64) -> Snapshot {
+        let gauge = GaugeVec::new(
+            Opts::new("stellaris_test", "A \"test\" gauge"),
+            &["save_name", "country"],
+        )
+        .unwrap();
+        gauge
+            .with_label_values(&["campaign", "United \"Nations\""])
+            .set(value);
+        Snapshot {
+            timestamp,
+            families: gauge.collect(),
+        }
+    }
+
+    #[test]
+    fn test_write_openmetrics() {
+        let mut out = Vec::new();
+        let written =
+            write_openmetrics(&mut out, &[snapshot(1000, 1.5), snapshot(1060, 2.0)]).unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"# HELP stellaris_test A \"test\" gauge
+# TYPE stellaris_test gauge
+stellaris_test{country="United \"Nations\"",save_name="campaign"} 1.5 1000
+stellaris_test{country="United \"Nations\"",save_name="campaign"} 2 1060
+# EOF
+"#
+        );
+    }
+}
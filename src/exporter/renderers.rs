@@ -11,7 +11,7 @@ use std::{
 };
 use walkdir::WalkDir;
 
-use crate::exporter::configs::CONFIGS;
+use crate::{exporter::configs::CONFIGS, models::common::LocalizedText};
 
 lazy_static! {
     // r#"F:\SteamLibrary\steamapps\common\Stellaris\localisation\english\"#.to_string();
@@ -241,3 +241,33 @@ pub fn transform_input_name(input: &Value) -> Result<String, Box<dyn Error>> {
     }
     Ok(renderer.transform_input_to_readable(input))
 }
+
+/// Renders a localised name of the typed model.
+pub fn transform_localized(text: &LocalizedText) -> Result<String, Box<dyn Error>> {
+    transform_input_name(&localized_to_value(text))
+}
+
+/// The shape `transform_input_to_readable` expects, with `literal` written as `"yes"`.
+fn localized_to_value(text: &LocalizedText) -> Value {
+    let mut object = serde_json::Map::new();
+    object.insert("key".to_string(), Value::from(text.key.as_str()));
+    if text.literal {
+        object.insert("literal".to_string(), Value::from("yes"));
+    }
+    if !text.variables.is_empty() {
+        let variables = text
+            .variables
+            .iter()
+            .map(|variable| {
+                let mut entry = serde_json::Map::new();
+                entry.insert("key".to_string(), Value::from(variable.key.as_str()));
+                if let Some(value) = &variable.value {
+                    entry.insert("value".to_string(), localized_to_value(value));
+                }
+                Value::Object(entry)
+            })
+            .collect();
+        object.insert("variables".to_string(), Value::Array(variables));
+    }
+    Value::Object(object)
+}
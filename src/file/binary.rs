@@ -0,0 +1,359 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use jomini::{
+    binary::{BinaryFlavor, FailedResolveStrategy, TokenResolver},
+    BinaryDeserializer, Encoding, Utf8Encoding,
+};
+use log::{debug, error, warn};
+use once_cell::sync::Lazy;
+use serde::{
+    de::{DeserializeOwned, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Value};
+
+use crate::{exporter::configs::CONFIGS, file::error::SaveError};
+
+/// Token table read from the `token_table` path of the `config.toml`.
+static TOKENS: Lazy<Option<TokenTable>> = Lazy::new(|| {
+    let path = CONFIGS.lock().unwrap().paths.token_table.clone();
+    if path.is_empty() {
+        return None;
+    }
+    match TokenTable::from_file(Path::new(&path)) {
+        Ok(table) => {
+            debug!("Loaded {} binary tokens from {}", table.len(), path);
+            Some(table)
+        }
+        Err(e) => {
+            error!("Could not read the token table at {}: {}", path, e);
+            None
+        }
+    }
+});
+
+/// How binary Stellaris data encodes text and rational numbers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StellarisFlavor;
+
+impl BinaryFlavor for StellarisFlavor {
+    fn visit_f32(&self, data: [u8; 4]) -> f32 {
+        i32::from_le_bytes(data) as f32 / 1000.0
+    }
+
+    fn visit_f64(&self, data: [u8; 8]) -> f64 {
+        i64::from_le_bytes(data) as f64 / 100_000.0
+    }
+}
+
+impl Encoding for StellarisFlavor {
+    fn decode<'a>(&self, data: &'a [u8]) -> Cow<'a, str> {
+        Utf8Encoding::decode(data)
+    }
+}
+
+/// Maps the 16 bit field ids of the binary format to their names.
+#[derive(Debug, Default)]
+pub struct TokenTable {
+    tokens: HashMap<u16, String>,
+}
+
+impl TokenTable {
+    pub fn from_file(path: &Path) -> std::io::Result<TokenTable> {
+        Ok(TokenTable::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Reads one `id name` (or `name id`) pair per line, ids being decimal or
+    /// `0x` prefixed hexadecimal. Empty lines and `#` comments are skipped.
+    pub fn parse(content: &str) -> TokenTable {
+        let mut tokens = HashMap::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(first), Some(second)) = (parts.next(), parts.next()) else {
+                warn!("Skipping malformed token table line: {:?}", line);
+                continue;
+            };
+            match (parse_token_id(first), parse_token_id(second)) {
+                (Some(id), _) => tokens.insert(id, second.to_string()),
+                (None, Some(id)) => tokens.insert(id, first.to_string()),
+                (None, None) => {
+                    warn!("Skipping malformed token table line: {:?}", line);
+                    continue;
+                }
+            };
+        }
+        TokenTable { tokens }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+}
+
+impl TokenResolver for TokenTable {
+    fn resolve(&self, token: u16) -> Option<&str> {
+        self.tokens.get(&token).map(|s| s.as_str())
+    }
+}
+
+/// Resolves the tokens of a single decode, remembering the ids missing from
+/// the table so they are reported with the save they were found in.
+struct TrackedResolver<'a> {
+    tokens: &'a TokenTable,
+    unknown: RefCell<BTreeSet<u16>>,
+}
+
+impl<'a> TrackedResolver<'a> {
+    fn new(tokens: &'a TokenTable) -> TrackedResolver<'a> {
+        TrackedResolver {
+            tokens,
+            unknown: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    fn into_unknown(self) -> Vec<u16> {
+        self.unknown.into_inner().into_iter().collect()
+    }
+}
+
+impl TokenResolver for TrackedResolver<'_> {
+    fn resolve(&self, token: u16) -> Option<&str> {
+        let resolved = self.tokens.resolve(token);
+        if resolved.is_none() {
+            self.unknown.borrow_mut().insert(token);
+        }
+        resolved
+    }
+}
+
+fn parse_token_id(s: &str) -> Option<u16> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Plaintext entries start with `key=`, binary ones with a 16 bit token id
+/// followed by the binary equals token (`0x0001`).
+pub fn is_binary(data: &[u8]) -> bool {
+    data.get(2..4) == Some(&[0x01, 0x00]) || data.iter().take(1024).any(|b| *b == 0)
+}
+
+/// Decodes a binary entry of the save with the configured token table and
+/// returns it as the same JSON the plaintext parser produces.
+pub fn binary_to_json(data: &[u8]) -> Result<String, SaveError> {
+    let decoded = decode_with(data, configured_tokens()?)?;
+    serde_json::to_string_pretty(&decoded).map_err(|e| SaveError::Binary(e.to_string()))
+}
+
+/// Deserializes a binary entry of the save with the configured token table.
+pub fn binary_to_model<T: DeserializeOwned>(data: &[u8]) -> Result<T, SaveError> {
+    deserialize_with(data, configured_tokens()?)
+}
+
+fn configured_tokens() -> Result<&'static TokenTable, SaveError> {
+    TOKENS.as_ref().ok_or_else(|| {
+        SaveError::Binary("binary save found but no token_table is configured".to_string())
+    })
+}
+
+/// A JSON value that groups repeated keys into an array, like the plaintext
+/// parser does with `DuplicateKeyMode::Group`.
+struct Grouped(Value);
+
+/// The root of a save, which the binary format only exposes as a map.
+struct GroupedRoot(Value);
+
+struct GroupedVisitor;
+
+impl<'de> Visitor<'de> for GroupedVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a clausewitz value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(Grouped(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut values = Map::new();
+        let mut grouped = BTreeSet::new();
+        while let Some((key, Grouped(value))) = map.next_entry::<String, Grouped>()? {
+            match values.get_mut(&key) {
+                Some(Value::Array(group)) if grouped.contains(&key) => group.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                    grouped.insert(key);
+                }
+                None => {
+                    values.insert(key, value);
+                }
+            }
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+impl<'de> Deserialize<'de> for Grouped {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(GroupedVisitor).map(Grouped)
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupedRoot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(GroupedVisitor)
+            .map(GroupedRoot)
+    }
+}
+
+fn decode_with(data: &[u8], tokens: &TokenTable) -> Result<Value, SaveError> {
+    let GroupedRoot(decoded) = deserialize_with(data, tokens)?;
+    Ok(decoded)
+}
+
+fn deserialize_with<T: DeserializeOwned>(data: &[u8], tokens: &TokenTable) -> Result<T, SaveError> {
+    let mut builder = BinaryDeserializer::builder_flavor(StellarisFlavor);
+    builder.on_failed_resolve(FailedResolveStrategy::Stringify);
+    let resolver = TrackedResolver::new(tokens);
+    let decoded: Result<T, _> = builder.deserialize_slice(data, &resolver);
+
+    let unknown = resolver.into_unknown();
+    if !unknown.is_empty() {
+        warn!(
+            "{} unknown binary tokens were kept as hex strings: {}",
+            unknown.len(),
+            unknown
+                .iter()
+                .take(20)
+                .map(|t| format!("0x{:x}", t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    decoded.map_err(|e| SaveError::Binary(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::gamestate_model::LenientGamestate;
+
+    #[test]
+    fn test_parse_token_table() {
+        let table =
+            TokenTable::parse("# comment\n0x2d82 field1\n\n300 field2\nfield3 0x00ff\nbroken\n");
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.resolve(0x2d82), Some("field1"));
+        assert_eq!(table.resolve(300), Some("field2"));
+        assert_eq!(table.resolve(0xff), Some("field3"));
+        assert_eq!(table.resolve(0x1234), None);
+
+        // Each decode keeps the ids it could not resolve to itself.
+        let first = TrackedResolver::new(&table);
+        let second = TrackedResolver::new(&table);
+        assert_eq!(first.resolve(0x1234), None);
+        assert_eq!(first.resolve(0x2d82), Some("field1"));
+        assert_eq!(second.resolve(0x4321), None);
+        assert_eq!(first.into_unknown(), vec![0x1234]);
+        assert_eq!(second.into_unknown(), vec![0x4321]);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(&[0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00]));
+        assert!(!is_binary(b"version=\"Gemini v3.8.4\""));
+    }
+
+    #[test]
+    fn test_decode_binary_with_unknown_tokens() {
+        let table = TokenTable::parse("0x2d82 field1");
+        let data = [
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x03, 0x00, 0x45, 0x4e, 0x47, // field1="ENG"
+            0x83, 0x2d, 0x01, 0x00, 0x0c, 0x00, 0x59, 0x00, 0x00, 0x00, // 0x2d83=89
+        ];
+        assert_eq!(
+            decode_with(&data, &table).unwrap().to_string(),
+            r#"{"0x2d83":89,"field1":"ENG"}"#
+        );
+    }
+
+    #[test]
+    fn test_decode_binary_groups_repeated_keys() {
+        let table = TokenTable::parse("0x2d82 technology");
+        let data = [
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x61, // technology="a"
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x62, // technology="b"
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x63, // technology="c"
+        ];
+        assert_eq!(
+            decode_with(&data, &table).unwrap().to_string(),
+            r#"{"technology":["a","b","c"]}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_binary_into_model() {
+        let table = TokenTable::parse("0x2d82 version\n0x2d83 tick");
+        let data = [
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x03, 0x00, 0x45, 0x4e, 0x47, // version="ENG"
+            0x83, 0x2d, 0x01, 0x00, 0x0c, 0x00, 0x59, 0x00, 0x00, 0x00, // tick=89
+            0x84, 0x2d, 0x01, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x00, // 0x2d84=1
+        ];
+        let LenientGamestate(model) = deserialize_with(&data, &table).unwrap();
+        assert_eq!(model.version.as_deref(), Some("ENG"));
+        assert_eq!(model.tick, Some(89));
+
+        // A section that does not match the model is skipped on its own.
+        let data = [
+            0x83, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x01, 0x00, 0x78, // tick="x"
+            0x82, 0x2d, 0x01, 0x00, 0x0f, 0x00, 0x03, 0x00, 0x45, 0x4e, 0x47, // version="ENG"
+        ];
+        let LenientGamestate(model) = deserialize_with(&data, &table).unwrap();
+        assert_eq!(model.version.as_deref(), Some("ENG"));
+        assert_eq!(model.tick, None);
+    }
+}
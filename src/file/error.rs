@@ -0,0 +1,56 @@
+use std::{path::PathBuf, string::FromUtf8Error};
+
+use derive_more::{Display, From};
+
+use crate::parser::ParseError;
+
+/// Everything that can go wrong between opening a `.sav` file and handing its
+/// gamestate to the extractors.
+#[derive(Debug, Display, From)]
+pub enum SaveError {
+    #[display(fmt = "Could not determine game ID of {}", _0)]
+    #[from(ignore)]
+    GameId(String),
+    #[display(fmt = "Save path {:?} is not valid UTF-8", _0)]
+    #[from(ignore)]
+    InvalidPath(PathBuf),
+    #[display(fmt = "Failed to open save file: {}", _0)]
+    Io(std::io::Error),
+    #[display(fmt = "Failed to read zip archive: {}", _0)]
+    Zip(zip::result::ZipError),
+    #[display(fmt = "Could not locate {} in zip archive", _0)]
+    MissingEntry(&'static str),
+    #[display(fmt = "Save content is not valid UTF-8: {}", _0)]
+    Utf8(FromUtf8Error),
+    #[display(fmt = "Failed to parse save: {}", _0)]
+    #[from(ignore)]
+    Parse(String),
+    #[display(fmt = "Syntax error in the {} of the save: {}", entry, error)]
+    #[from(ignore)]
+    Syntax {
+        entry: &'static str,
+        error: ParseError,
+    },
+    #[display(fmt = "Failed to decode binary save: {}", _0)]
+    #[from(ignore)]
+    Binary(String),
+}
+
+impl SaveError {
+    /// Short label used for the `reason` of the parse failures counter.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SaveError::GameId(_) => "game_id",
+            SaveError::InvalidPath(_) => "path",
+            SaveError::Io(_) => "io",
+            SaveError::Zip(_) => "zip",
+            SaveError::MissingEntry(_) => "missing_entry",
+            SaveError::Utf8(_) => "utf8",
+            SaveError::Parse(_) => "parse",
+            SaveError::Syntax { .. } => "syntax",
+            SaveError::Binary(_) => "binary",
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{debug, info, warn};
+
+use crate::{
+    exporter::{
+        configs::CONFIGS,
+        exporter::{replace_campaign_series, STELLARIS_SAVE_PARSE_FAILURES},
+        extractor::{extractors, Extractor},
+    },
+    file::{
+        error::SaveError,
+        save_handler::{parse_save_file_2, save_json_to_file, stream_save_file},
+    },
+    history::store::record_campaign,
+    models::gamestate_model::Gamestate,
+    singletons::singletons::set_game_data,
+};
+
+/// Parses a single `.sav` file and feeds its gamestate through every extractor.
+///
+/// Failures are counted in `stellaris_save_parse_failures_total` before being returned.
+pub fn ingest_save_file(save_path: &Path) -> Result<(), SaveError> {
+    let result = ingest(save_path);
+    if let Err(e) = &result {
+        STELLARIS_SAVE_PARSE_FAILURES
+            .with_label_values(&[e.reason()])
+            .inc();
+    }
+    result
+}
+
+/// A save parsed into the gamestate model, ready for the extractors.
+pub struct LoadedSave {
+    pub game_id: String,
+    pub filename: String,
+    /// The gamestate as JSON, only kept when the dump is enabled.
+    pub json: Option<String>,
+    pub model: Box<Gamestate>,
+}
+
+impl LoadedSave {
+    pub fn date(&self) -> Option<&str> {
+        self.model.date.as_deref()
+    }
+}
+
+/// Parses a `.sav` file into the gamestate model, rendering it as JSON as well
+/// when `with_json` is set.
+///
+/// Without the JSON, the gamestate is streamed and only the sections read by
+/// the configured extractors are kept.
+pub fn load_save(save_path: &Path, with_json: bool) -> Result<LoadedSave, SaveError> {
+    let Some(path) = save_path.to_str() else {
+        return Err(SaveError::InvalidPath(save_path.to_path_buf()));
+    };
+
+    info!("Parsing save file: {}", path);
+    let content = match with_json {
+        true => parse_save_file_2(path, true)?,
+        false => {
+            let extractors = configured_extractors();
+            let sections: BTreeSet<&str> = extractors
+                .iter()
+                .flat_map(|extractor| extractor.sections.iter().copied())
+                .collect();
+            stream_save_file(path, &sections)?
+        }
+    };
+    debug!("Parsed Game ID: {}", &content.game_id);
+
+    Ok(LoadedSave {
+        game_id: content.game_id,
+        filename: save_path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or(path)
+            .to_string(),
+        json: content.json,
+        model: content.model,
+    })
+}
+
+/// Replaces the campaign's series with the ones extracted from `save`.
+pub fn export_save(save: &LoadedSave) {
+    let extractors = configured_extractors();
+    replace_campaign_series(&save.game_id, || {
+        for extractor in &extractors {
+            (extractor.extract)(&save.model, &save.game_id);
+        }
+    });
+}
+
+fn configured_extractors() -> Vec<Extractor> {
+    extractors(&CONFIGS.lock().unwrap().metrics)
+}
+
+fn ingest(save_path: &Path) -> Result<(), SaveError> {
+    let dump_json = CONFIGS.lock().unwrap().dump.json;
+    let save = load_save(save_path, dump_json)?;
+    export_save(&save);
+    if let Some(date) = save.date() {
+        record_campaign(&save.game_id, date, &save.filename);
+    }
+    if let Some(json) = save.json {
+        if let Err(e) = save_json_to_file(&json) {
+            warn!("Could not write gamestate.json: {}", e);
+        }
+        let _ = set_game_data(json);
+    }
+
+    info!("Save file parsed");
+    Ok(())
+}
+
+/// Ingests the newest save of every campaign folder found under `save_location`,
+/// so the metrics are populated before the game writes its next autosave.
+pub fn scan_existing_saves(save_location: &Path) {
+    info!("Scanning existing saves in {:?}", save_location);
+    let saves = latest_save_per_campaign(save_location);
+    debug!("Found {} campaigns with saves", saves.len());
+
+    for save in saves {
+        if let Err(e) = ingest_save_file(&save) {
+            warn!("Could not ingest existing save {:?}: {}", save, e);
+        }
+    }
+}
+
+/// Returns the most recently modified `.sav` file of each campaign folder.
+pub fn latest_save_per_campaign(save_location: &Path) -> Vec<PathBuf> {
+    let Ok(campaigns) = fs::read_dir(save_location) else {
+        warn!("Save location {:?} could not be read", save_location);
+        return Vec::new();
+    };
+
+    campaigns
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|campaign| latest_save_in(&campaign))
+        .collect()
+}
+
+fn latest_save_in(campaign: &Path) -> Option<PathBuf> {
+    fs::read_dir(campaign)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_save_file(path))
+        .map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, path)
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+pub fn is_save_file(path: &Path) -> bool {
+    path.is_file() && has_save_extension(path)
+}
+
+pub fn has_save_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "sav")
+}
@@ -1,2 +1,6 @@
+pub mod binary;
+pub mod error;
+pub mod ingest;
 pub mod save_handler;
+pub mod stream;
 pub mod watcher;
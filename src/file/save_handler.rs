@@ -1,9 +1,21 @@
-use futures::future::ok;
-use jomini::TextTape;
-use log::{debug, error, trace};
+use jomini::{
+    json::{DuplicateKeyMode, JsonOptions},
+    text::de::TextDeserializer,
+    TextTape,
+};
+use log::{error, trace};
 
-use crate::{file_io::load_save_content, models::gamestate_model::Gamestate, parser::parse_save};
-use std::{error::Error, fs::File, io::Write, process::exit, string::FromUtf8Error};
+use crate::{
+    file::{
+        binary::{binary_to_json, binary_to_model},
+        error::SaveError,
+        stream::read_gamestate,
+    },
+    file_io::{load_save_content, open_entry, open_save, SaveContent},
+    models::gamestate_model::{Gamestate, LenientGamestate},
+    parser::{parse_file, parse_save},
+};
+use std::{collections::BTreeSet, fs::File, io::Write, string::FromUtf8Error};
 
 pub struct GameContent {
     pub filename: String,
@@ -12,67 +24,119 @@ pub struct GameContent {
     pub gamestate: Box<String>,
 }
 
-pub fn parse_save_file_2(save_path: &str) -> Result<GameContent, String> {
-    let save_file = match load_save_content(save_path) {
-        Ok(sf) => sf,
-        Err(_) => exit(1),
-    };
+/// A save deserialized into the gamestate model.
+pub struct ParsedSave {
+    pub game_id: String,
+    pub model: Box<Gamestate>,
+    /// The gamestate as JSON, only rendered when asked for.
+    pub json: Option<String>,
+}
 
-    let meta = match parse_content(save_file.meta.as_str()) {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Error while parsing contents of Meta: {:#?}", e);
-            return Err("Parsing Meta was not possible".to_string());
-        }
-    };
-    let gamestate = match parse_content(save_file.gamestate.as_str()) {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Error while parsing contents of Meta: {:#?}", e);
-            return Err("Parsing Meta was not possible".to_string());
-        }
-    };
+/// Reads a `.sav` file and deserializes its gamestate into the model, going
+/// over the parsed tape once. The JSON rendering is skipped unless `with_json`
+/// is set, as it is several times bigger than the save itself.
+pub fn parse_save_file_2(save_path: &str, with_json: bool) -> Result<ParsedSave, SaveError> {
+    let save_file = load_save_content(save_path)?;
+
+    let (model, json) = parse_gamestate(&save_file.gamestate, with_json).map_err(|e| {
+        error!("Error while parsing contents of Gamestate: {}", e);
+        e
+    })?;
 
-    Ok(GameContent {
-        filename: save_file.filename,
+    Ok(ParsedSave {
         game_id: save_file.game_id,
-        meta,
-        gamestate,
+        model: Box::new(model),
+        json,
     })
 }
 
-fn parse_content(content: &str) -> Result<Box<String>, Box<dyn Error>> {
-    let tape = TextTape::from_slice(content.as_bytes())?;
-    let reader = tape.utf8_reader();
-    let actual = reader.json().to_string();
-    Ok(Box::new(actual.to_string()))
+/// Reads a `.sav` file into the model while its gamestate is decompressed,
+/// keeping only the top level `sections` the extractors read.
+pub fn stream_save_file(
+    save_path: &str,
+    sections: &BTreeSet<&str>,
+) -> Result<ParsedSave, SaveError> {
+    let (game_id, mut archive) = open_save(save_path)?;
+    let entry = open_entry(&mut archive, "gamestate")?;
+
+    let model = read_gamestate(entry, sections).map_err(|e| {
+        error!("Error while parsing contents of Gamestate: {}", e);
+        e
+    })?;
+
+    Ok(ParsedSave {
+        game_id,
+        model: Box::new(model),
+        json: None,
+    })
+}
+
+/// Deserializes the `gamestate` entry straight into the typed model, and into
+/// JSON as well when `with_json` is set. Sections that do not match the model
+/// are left empty.
+pub fn parse_gamestate(
+    content: &SaveContent,
+    with_json: bool,
+) -> Result<(Gamestate, Option<String>), SaveError> {
+    match content {
+        SaveContent::Text(text) => {
+            let tape = TextTape::from_slice(text.as_bytes()).map_err(|e| tape_error(text, e))?;
+            let LenientGamestate(model) = TextDeserializer::from_utf8_tape(&tape)
+                .deserialize()
+                .map_err(|e| SaveError::Parse(e.to_string()))?;
+            let json = with_json.then(|| tape_to_json(&tape));
+            Ok((model, json))
+        }
+        SaveContent::Binary(data) => {
+            let LenientGamestate(model) = binary_to_model(data)?;
+            let json = match with_json {
+                true => Some(binary_to_json(data)?),
+                false => None,
+            };
+            Ok((model, json))
+        }
+    }
+}
+
+/// The error of a gamestate text jomini could not read. jomini only knows the
+/// offset, the parser tells where and why.
+pub fn tape_error(text: &str, error: jomini::Error) -> SaveError {
+    match parse_file(text) {
+        Err(error) => SaveError::Syntax {
+            entry: "gamestate",
+            error,
+        },
+        Ok(_) => SaveError::Parse(error.to_string()),
+    }
+}
+
+fn tape_to_json(tape: &TextTape) -> String {
+    // Repeated keys (`technology=a technology=b`) are kept as arrays
+    // instead of only keeping the last one.
+    let options = JsonOptions::new()
+        .with_duplicate_keys(DuplicateKeyMode::Group)
+        .with_prettyprint(true);
+    tape.utf8_reader().json().with_options(options).to_string()
 }
 
-pub fn parse_save_file(save_path: &str) -> Result<GameContent, String> {
-    let save_file = match load_save_content(save_path) {
-        Ok(sf) => sf,
-        Err(_) => exit(1),
-    };
+pub fn parse_save_file(save_path: &str) -> Result<GameContent, SaveError> {
+    let save_file = load_save_content(save_path)?;
     match parse_save(&save_file) {
         Ok(parsed_save) => {
-            let obj = parsed_save.gamestate;
-            match serde_json::to_string(&obj) {
-                Ok(js) => {
-                    return Ok(GameContent {
-                        filename: save_file.filename.clone(),
-                        game_id: parsed_save.game_id,
-                        meta: Box::new(save_file.meta),
-                        gamestate: Box::new(js),
-                    })
-                }
-                Err(e) => return Err(format!("Error while converting to json: {}", e)),
-            };
+            let js = serde_json::to_string(&parsed_save.gamestate)
+                .map_err(|e| SaveError::Parse(format!("Error while converting to json: {}", e)))?;
+            Ok(GameContent {
+                filename: save_file.filename.clone(),
+                game_id: parsed_save.game_id,
+                meta: Box::new(save_file.meta.as_text().unwrap_or_default().to_string()),
+                gamestate: Box::new(js),
+            })
         }
-        Err(msg) => {
-            error!("Failed to parse {}: {}", save_file.filename, msg);
-            return Err(format!("Failed to parse {}: {}", save_file.filename, msg));
+        Err(e) => {
+            error!("Failed to parse {}: {}", save_file.filename, e);
+            Err(e)
         }
-    };
+    }
 }
 
 pub fn string_to_json(string: &str) -> serde_json::Result<Box<serde_json::Value>> {
@@ -80,15 +144,10 @@ pub fn string_to_json(string: &str) -> serde_json::Result<Box<serde_json::Value>
     return Ok(result);
 }
 
-pub fn map_to_model(json: Box<String>) -> serde_json::Result<Box<Gamestate>> {
-    let gm: Box<Gamestate> = Box::new(serde_json::from_str(&*json)?);
-    return Ok(gm);
-}
-
-pub fn save_json_to_file(json: &Box<String>) -> std::io::Result<()> {
+pub fn save_json_to_file(json: &str) -> std::io::Result<()> {
     trace!("Saving Save information to json file");
     let mut file = File::create("gamestate.json")?;
-    let _ = file.write_all(json.as_bytes())?;
+    file.write_all(json.as_bytes())?;
     trace!("Save done");
     Ok(())
 }
@@ -100,9 +159,3 @@ pub fn read_from_json_file() -> Result<Box<String>, FromUtf8Error> {
         Err(e) => Err(e),
     }
 }
-
-pub fn convert_to_pretty_str(content: String) -> serde_json::Result<String> {
-    let json: serde_json::Value = serde_json::from_str(&content.as_str())?;
-    let pretty = serde_json::to_string_pretty(&json).unwrap();
-    Ok(pretty)
-}
@@ -0,0 +1,540 @@
+use std::{
+    collections::BTreeSet,
+    io::{BufRead, BufReader, Read},
+};
+
+use jomini::{text::de::TextDeserializer, TextTape};
+use log::trace;
+
+use crate::{
+    file::{
+        binary::{binary_to_model, is_binary},
+        error::SaveError,
+        save_handler::tape_error,
+    },
+    models::gamestate_model::{Gamestate, LenientGamestate},
+};
+
+/// Bytes read from the archive at once.
+const BUFFER_SIZE: usize = 1 << 16;
+
+/// A top level `key=value` of the gamestate, like `country={ ... }`.
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    pub key: String,
+    /// The whole `key=value` text, without the comments around it.
+    pub text: String,
+    /// Byte offset of the section in the entry.
+    pub offset: usize,
+    /// 1-based line and column of the section in the entry.
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Streams the `gamestate` entry into the model, one top level section at a
+/// time. Only the sections in `wanted` are kept, so the memory used is the
+/// model plus the biggest of them instead of the whole entry.
+///
+/// Binary entries are still decoded in one go.
+pub fn read_gamestate<R: Read>(entry: R, wanted: &BTreeSet<&str>) -> Result<Gamestate, SaveError> {
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, entry);
+    if is_binary(reader.fill_buf()?) {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        return binary_to_model(&data).map(|LenientGamestate(gamestate)| gamestate);
+    }
+
+    let mut gamestate = Gamestate::default();
+    for section in Sections::new(reader, |key| wanted.contains(key)) {
+        let section = section?;
+        trace!(
+            "Read the {} section, {} bytes at line {}",
+            section.key,
+            section.text.len(),
+            section.line
+        );
+        gamestate.take_section(&section.key, read_section(&section)?);
+    }
+    Ok(gamestate)
+}
+
+fn read_section(section: &Section) -> Result<Gamestate, SaveError> {
+    let tape = TextTape::from_slice(section.text.as_bytes()).map_err(|e| {
+        match tape_error(&section.text, e) {
+            SaveError::Syntax { entry, error } => SaveError::Syntax {
+                entry,
+                error: error.starting_at(section.offset, section.line, section.column),
+            },
+            e => e,
+        }
+    })?;
+    TextDeserializer::from_utf8_tape(&tape)
+        .deserialize()
+        .map(|LenientGamestate(gamestate)| gamestate)
+        .map_err(|e| SaveError::Parse(format!("{} section: {}", section.key, e)))
+}
+
+/// Splits a plaintext entry into its top level sections while reading it.
+///
+/// Only the braces, quotes and comments are looked at, checking the syntax is
+/// left to the parser of each section. The sections `wanted` rejects are read
+/// past without being kept.
+pub struct Sections<R, F> {
+    reader: R,
+    splitter: Splitter<F>,
+}
+
+impl<R: BufRead, F: FnMut(&str) -> bool> Sections<R, F> {
+    pub fn new(reader: R, wanted: F) -> Sections<R, F> {
+        Sections {
+            reader,
+            splitter: Splitter {
+                wanted,
+                state: State::Between,
+                depth: 0,
+                quoted: false,
+                escaped: false,
+                comment: false,
+                offset: 0,
+                line: 1,
+                column: 1,
+                pending: None,
+            },
+        }
+    }
+}
+
+impl<R: BufRead, F: FnMut(&str) -> bool> Iterator for Sections<R, F> {
+    type Item = Result<Section, SaveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let buffer = match self.reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if buffer.is_empty() {
+                // An unterminated section is still handed over, for its
+                // parser to tell what is missing.
+                return self.splitter.end().map(Pending::into_section);
+            }
+
+            let mut read = 0;
+            let mut finished = None;
+            for &byte in buffer {
+                read += 1;
+                if let Some(section) = self.splitter.step(byte) {
+                    finished = Some(section);
+                    break;
+                }
+            }
+            self.reader.consume(read);
+            if let Some(section) = finished {
+                return Some(section.into_section());
+            }
+        }
+    }
+}
+
+/// Where the splitter is in the top level `key=value` being read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Between,
+    Key,
+    Operator,
+    Value,
+    AfterValue,
+}
+
+struct Splitter<F> {
+    wanted: F,
+    state: State,
+    /// Depth of the braces, 0 being the top level.
+    depth: usize,
+    quoted: bool,
+    escaped: bool,
+    comment: bool,
+    /// Position of the byte being read.
+    offset: usize,
+    line: usize,
+    column: usize,
+    pending: Option<Pending>,
+}
+
+/// The section being read.
+struct Pending {
+    key: Vec<u8>,
+    text: Vec<u8>,
+    keep: bool,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Pending {
+    fn into_section(self) -> Result<Section, SaveError> {
+        Ok(Section {
+            key: String::from_utf8_lossy(&self.key).into_owned(),
+            text: String::from_utf8(self.text)?,
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        })
+    }
+}
+
+impl<F: FnMut(&str) -> bool> Splitter<F> {
+    /// Reads one byte, returning the previous section once `byte` starts the
+    /// next one.
+    fn step(&mut self, byte: u8) -> Option<Pending> {
+        let finished = self.read(byte);
+        self.offset += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // Columns count characters, not the continuation bytes of UTF-8.
+            self.column += 1;
+        }
+        finished
+    }
+
+    fn read(&mut self, byte: u8) -> Option<Pending> {
+        if self.comment {
+            if byte == b'\n' {
+                self.comment = false;
+                self.push(byte);
+            }
+            return None;
+        }
+
+        if self.quoted {
+            self.push(byte);
+            if self.state == State::Key && self.depth == 0 && byte != b'"' {
+                self.push_key(byte);
+            }
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.quoted = false;
+                if self.depth == 0 {
+                    self.end_token();
+                }
+            }
+            return None;
+        }
+
+        if self.depth > 0 {
+            match byte {
+                b'#' => self.comment = true,
+                b'"' => self.quoted = true,
+                b'{' => self.depth += 1,
+                b'}' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        self.state = State::AfterValue;
+                    }
+                }
+                _ => {}
+            }
+            if byte != b'#' {
+                self.push(byte);
+            }
+            return None;
+        }
+
+        if byte == b'#' {
+            self.comment = true;
+            self.end_token();
+            return None;
+        }
+        let whitespace = byte.is_ascii_whitespace();
+        let operator = matches!(byte, b'=' | b'<' | b'>' | b'!' | b'?');
+        match self.state {
+            State::Between if whitespace => None,
+            State::Between => {
+                self.start(byte);
+                None
+            }
+            State::AfterValue if whitespace => None,
+            State::AfterValue if !operator && !matches!(byte, b'{' | b'}') => {
+                // A new key: the previous section is complete.
+                let previous = self.pending.take();
+                self.start(byte);
+                previous.filter(|pending| pending.keep)
+            }
+            State::Key if !whitespace && !operator && !matches!(byte, b'{' | b'}' | b'"') => {
+                self.push(byte);
+                self.push_key(byte);
+                None
+            }
+            State::Key => {
+                self.end_token();
+                self.value_byte(byte);
+                None
+            }
+            State::Value if whitespace => {
+                self.state = State::AfterValue;
+                None
+            }
+            _ => {
+                self.value_byte(byte);
+                None
+            }
+        }
+    }
+
+    /// Starts a section with its first byte.
+    fn start(&mut self, byte: u8) {
+        self.pending = Some(Pending {
+            key: Vec::new(),
+            text: Vec::new(),
+            keep: true,
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        });
+        self.state = State::Key;
+        match byte {
+            b'"' => {
+                self.push(byte);
+                self.quoted = true;
+            }
+            b'{' | b'}' => {
+                // A value without a key, left for the parser to report.
+                self.end_token();
+                self.value_byte(byte);
+            }
+            _ => {
+                self.push(byte);
+                self.push_key(byte);
+            }
+        }
+    }
+
+    /// Reads a top level byte that is part of the operator or of the value.
+    fn value_byte(&mut self, byte: u8) {
+        if byte.is_ascii_whitespace() {
+            if self.state == State::Operator {
+                self.push(byte);
+            }
+            return;
+        }
+        if self.state == State::AfterValue {
+            // Colors (`rgb { 1 2 3 }`) are a token followed by a block.
+            self.push(b' ');
+        }
+        self.push(byte);
+        self.state = match byte {
+            // `key=other_key=value` is read as `key=value` by the parser.
+            b'=' | b'<' | b'>' | b'!' | b'?' => State::Operator,
+            b'"' => {
+                self.quoted = true;
+                State::Value
+            }
+            b'{' => {
+                self.depth += 1;
+                State::Value
+            }
+            b'}' => State::AfterValue,
+            _ => State::Value,
+        };
+    }
+
+    /// Ends the key or the value token being read.
+    fn end_token(&mut self) {
+        self.state = match self.state {
+            State::Key => {
+                self.decide();
+                State::Operator
+            }
+            State::Value => State::AfterValue,
+            state => state,
+        };
+    }
+
+    /// Stops keeping the text of the section once its key is not wanted.
+    fn decide(&mut self) {
+        if let Some(pending) = &mut self.pending {
+            let key = String::from_utf8_lossy(&pending.key);
+            pending.keep = (self.wanted)(&key);
+            if !pending.keep {
+                pending.text = Vec::new();
+            }
+        }
+    }
+
+    /// Hands over the last section at the end of the input, when wanted.
+    fn end(&mut self) -> Option<Pending> {
+        if self.state == State::Key {
+            self.decide();
+        }
+        self.pending.take().filter(|pending| pending.keep)
+    }
+
+    fn push(&mut self, byte: u8) {
+        if let Some(pending) = self.pending.as_mut().filter(|pending| pending.keep) {
+            pending.text.push(byte);
+        }
+    }
+
+    fn push_key(&mut self, byte: u8) {
+        if let Some(pending) = &mut self.pending {
+            pending.key.push(byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::exporter::{configs::MetricsConfig, extractor::extractors};
+
+    /// Splits `text` with a tiny buffer, so sections span several reads.
+    fn split(text: &str, wanted: impl FnMut(&str) -> bool) -> Vec<Section> {
+        let reader = BufReader::with_capacity(3, Cursor::new(text.as_bytes()));
+        Sections::new(reader, wanted)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn keys_and_texts(sections: &[Section]) -> Vec<(&str, &str)> {
+        sections
+            .iter()
+            .map(|s| (s.key.as_str(), s.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_sections() {
+        let sections = split(
+            "# header\nversion=\"v3.8\" tick=12\ncountry={\n\t0={ name=\"a } b\" } # 1={\n}\n\
+             \"quoted key\" = { 1 2 }\ncolor = rgb { 1 2 3 } event_id= scope={ id=0 }\nlast=yes",
+            |_| true,
+        );
+        assert_eq!(
+            keys_and_texts(&sections),
+            vec![
+                ("version", "version=\"v3.8\""),
+                ("tick", "tick=12"),
+                ("country", "country={\n\t0={ name=\"a } b\" } \n}"),
+                ("quoted key", "\"quoted key\" = { 1 2 }"),
+                ("color", "color = rgb { 1 2 3 }"),
+                ("event_id", "event_id= scope={ id=0 }"),
+                ("last", "last=yes"),
+            ]
+        );
+        let country = &sections[2];
+        assert_eq!((country.offset, country.line, country.column), (32, 3, 1));
+        assert_eq!((sections[1].line, sections[1].column), (2, 16));
+    }
+
+    #[test]
+    fn test_split_skips_unwanted_sections() {
+        let mut asked = Vec::new();
+        let sections = split("a={ x=1 } b={ y={ 2 } } c=3 d", |key| {
+            asked.push(key.to_string());
+            key != "b"
+        });
+        assert_eq!(asked, vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            keys_and_texts(&sections),
+            vec![("a", "a={ x=1 }"), ("c", "c=3"), ("d", "d")]
+        );
+        // An unterminated section is kept for the parser to report.
+        assert_eq!(
+            keys_and_texts(&split("a=1 b={ c=\"}", |_| true)),
+            vec![("a", "a=1"), ("b", "b={ c=\"}")]
+        );
+    }
+
+    #[test]
+    fn test_read_gamestate() {
+        let text = r#"version="Gemini v3.8.4" date="2210.01.01" ai={ huge={ 1 2 3 } }
+            country={
+                0={ name={ key="My Empire" literal=yes } military_power=12 }
+                1=none
+            }
+            pop={ 5={ species=1 planet=2 } }"#;
+        let wanted = BTreeSet::from(["version", "date", "country", "pop"]);
+        let gm = read_gamestate(Cursor::new(text), &wanted).unwrap();
+        assert_eq!(gm.version.as_deref(), Some("Gemini v3.8.4"));
+        assert_eq!(gm.date.as_deref(), Some("2210.01.01"));
+        assert_eq!(gm.country.len(), 1);
+        assert_eq!(gm.country.get(0).unwrap().military_power, Some(12.0));
+        assert_eq!(gm.pop.len(), 1);
+
+        let gm = read_gamestate(Cursor::new(text), &BTreeSet::from(["date"])).unwrap();
+        assert!(gm.version.is_none() && gm.country.is_empty());
+    }
+
+    #[test]
+    fn test_read_gamestate_syntax_error() {
+        let text = "version=\"v3.8\"\ncountry={\n\t0={ name=\"a }\n}\npop={}";
+        let error = read_gamestate(Cursor::new(text), &BTreeSet::from(["country"])).unwrap_err();
+        let SaveError::Syntax { error, .. } = error else {
+            panic!("expected a syntax error, got {}", error);
+        };
+        // Positions are in the whole entry, not in the section.
+        assert_eq!((error.offset, error.line, error.column), (text.len(), 5, 7));
+        assert_eq!(error.context[0].label, "quoted");
+        assert_eq!((error.context[0].line, error.context[0].column), (3, 11));
+    }
+
+    #[test]
+    fn test_extractor_sections_are_in_the_model() {
+        let model = serde_json::to_value(Gamestate::default()).unwrap();
+        let metrics = MetricsConfig {
+            planets: true,
+            fleets: true,
+        };
+        for extractor in extractors(&metrics) {
+            for section in extractor.sections {
+                assert!(model.get(section).is_some(), "{} is not read", section);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_every_extractor_section() {
+        // One entry in each section some extractor reads.
+        let text = r#"version="v3.8" date="2210.01.01" tick=89 required_dlcs={ "Utopia" }
+            country={ 0={ military_power=1 } }
+            war={ 0={ attacker_war_exhaustion=1 } }
+            fleet={ 0={ owner=0 } }
+            ships={ 0={ fleet=0 } }
+            ship_design={ 0={ ship_size="corvette" } }
+            megastructures={ 0={ type="gateway_final" owner=0 } }
+            planets={ planet={ 0={ owner=0 } } }
+            buildings={ 0={ type="building_capital" } }
+            pop={ 0={ planet=0 } }
+            leaders={ 0={ class="admiral" } }
+            species_db={ 0={ name={ key="Human" } } }
+            federation={ 0={ leader=0 } }
+            galactic_community={ members={ 0 } }
+            resolution={ { type="resolution_a" } }
+            market={ fluctuations={ 0 5 } }
+            galactic_object={ 0={ starbase=0 } }
+            starbase_mgr={ starbases={ 0={ owner=0 } } }"#;
+        let metrics = MetricsConfig {
+            planets: true,
+            fleets: true,
+        };
+        let extractors = extractors(&metrics);
+        let wanted: BTreeSet<&str> = extractors
+            .iter()
+            .flat_map(|extractor| extractor.sections.iter().copied())
+            .collect();
+
+        let gm = read_gamestate(Cursor::new(text), &wanted).unwrap();
+        let read = serde_json::to_value(gm).unwrap();
+        let empty = serde_json::to_value(Gamestate::default()).unwrap();
+        for section in wanted {
+            assert_ne!(read[section], empty[section], "{} was not read", section);
+        }
+    }
+}
@@ -1,20 +1,27 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
 use futures::{
     channel::mpsc::{channel, Receiver},
     SinkExt, StreamExt,
 };
 use log::{debug, error, info, trace, warn};
-use notify::{Config, Error, Event, RecommendedWatcher, RecursiveMode, Watcher};
-
-use crate::{
-    exporter::extractor::{get_country_infos, get_megastructures, get_wars},
-    file::save_handler::{
-        self, convert_to_pretty_str, parse_save_file, parse_save_file_2, save_json_to_file,
-    },
-    singletons::singletons::set_game_data,
+use notify::{event::EventKind, Config, Error, Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::file::{
+    error::SaveError,
+    ingest::{has_save_extension, ingest_save_file, scan_existing_saves},
 };
 
+/// How often the pending saves are checked for changes.
+const SETTLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long a save must keep the same size and mtime before it is parsed.
+const SETTLE_QUIET_PERIOD: Duration = Duration::from_secs(2);
+
 pub fn spawn_file_watcher(path: String) {
     // let profile = std::env::var("USERPROFILE").unwrap();
     info!("Starting file watcher");
@@ -51,62 +58,185 @@ pub async fn async_watch<P: AsRef<Path>>(path: P) -> notify::Result<()> {
     // below will be monitored for changes.
     watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
 
-    while let Some(res) = rx.next().await {
-        match res {
-            Ok(event) => {
-                trace!("New event: {:?}", event);
-                match event.kind {
-                    notify::event::EventKind::Create(_) => {
-                        trace!("New entry created in the save directory ");
-                        if event.paths.len() > 0 {
-                            let _path = event.paths.get(0);
-                            if let Some(created_path) = _path {
-                                trace!("Current Creation: {:?}", created_path);
-                                if created_path.is_file()
-                                    && created_path.extension().unwrap() == "sav"
-                                {
-                                    info!("Parsing new save file");
-                                    if let Some(path) = created_path.as_path().to_str() {
-                                        let content = match parse_save_file_2(path) {
-                                            Ok(c) => c,
-                                            Err(_) => return Ok(()),
-                                        };
-                                        debug!("Parsed Game ID: {}", &content.game_id);
-                                        debug!(
-                                            "Parsed content length: {:?}",
-                                            &content.gamestate.len()
-                                        );
-                                        // std::env::set_var("STELLARIS_FILENAME", content.filename);
-                                        // std::env::set_var("STELLARIS_GAMEID", content.game_id);
-
-                                        if let Ok(pretty) =
-                                            convert_to_pretty_str(*content.gamestate)
-                                        {
-                                            let model = save_handler::map_to_model(Box::new(
-                                                pretty.clone(),
-                                            ))
-                                            .unwrap();
-                                            get_country_infos(*model.clone(), &content.game_id);
-                                            get_megastructures(*model.clone(), &content.game_id);
-                                            get_wars(*model.clone(), &content.game_id);
-                                            let _ = set_game_data(pretty.clone());
-                                            let _ = save_json_to_file(&Box::new(pretty));
-                                        }
-
-                                        info!("Save file parsed");
-                                    } else {
-                                        warn!("The entry has no Path");
-                                    }
-                                }
-                            }
+    // Backfill from the saves already on disk. The watcher is registered first,
+    // so any save written during the scan is queued and handled right after.
+    let save_location: PathBuf = path.as_ref().to_path_buf();
+    if let Err(e) = tokio::task::spawn_blocking(move || scan_existing_saves(&save_location)).await {
+        error!("Startup scan of existing saves failed: {:?}", e);
+    }
+
+    let mut pending = PendingSaves::default();
+    let mut poll = tokio::time::interval(SETTLE_POLL_INTERVAL);
+
+    loop {
+        tokio::select! {
+            res = rx.next() => match res {
+                Some(Ok(event)) => {
+                    trace!("New event: {:?}", event);
+                    for save in changed_saves(&event) {
+                        trace!("Save changed on disk: {:?}", save);
+                        pending.touch(save, Instant::now());
+                    }
+                }
+                Some(Err(e)) => error!("watch error: {:?}", e),
+                None => break,
+            },
+            _ = poll.tick() => {
+                for save in pending.take_settled(Instant::now(), file_stamp) {
+                    info!("Parsing new save file");
+                    let target = save.clone();
+                    match tokio::task::spawn_blocking(move || ingest_save_file(&target)).await {
+                        Ok(Ok(())) => {}
+                        // Retrying will not help, report where the save is malformed.
+                        Ok(Err(e @ SaveError::Syntax { .. })) => {
+                            error!("Could not parse {:?}. {}", save, e)
                         }
+                        Ok(Err(e)) => warn!("Could not ingest {:?}: {}", save, e),
+                        Err(e) => error!("Ingest task for {:?} failed: {:?}", save, e),
                     }
-                    _ => {}
                 }
             }
-            Err(e) => println!("watch error: {:?}", e),
         }
     }
 
     Ok(())
 }
+
+/// Returns the `.sav` paths touched by a create, write or rename event.
+///
+/// Renames report both the old and the new name, the old one simply never
+/// settles because it no longer exists.
+fn changed_saves(event: &Event) -> Vec<PathBuf> {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => event
+            .paths
+            .iter()
+            .filter(|p| has_save_extension(p))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Size and modification time of a file, used to tell when a write is over.
+type FileStamp = (u64, SystemTime);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+struct PendingSave {
+    stamp: Option<FileStamp>,
+    stable_since: Instant,
+}
+
+/// Saves that changed on disk but are still waiting for the game to finish writing them.
+#[derive(Default)]
+struct PendingSaves {
+    saves: HashMap<PathBuf, PendingSave>,
+}
+
+impl PendingSaves {
+    /// Records a new event for `path`, restarting its quiet period.
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        let save = self.saves.entry(path).or_insert(PendingSave {
+            stamp: None,
+            stable_since: now,
+        });
+        save.stable_since = now;
+    }
+
+    /// Removes and returns every save whose size and mtime did not change for
+    /// the whole quiet period. Saves that vanished (e.g. renamed temp files)
+    /// are dropped.
+    fn take_settled<F>(&mut self, now: Instant, stamp_of: F) -> Vec<PathBuf>
+    where
+        F: Fn(&Path) -> Option<FileStamp>,
+    {
+        let mut settled = Vec::new();
+        self.saves.retain(|path, save| {
+            let Some(stamp) = stamp_of(path) else {
+                debug!("Pending save disappeared: {:?}", path);
+                return false;
+            };
+            if save.stamp != Some(stamp) {
+                save.stamp = Some(stamp);
+                save.stable_since = now;
+                return true;
+            }
+            if now.duration_since(save.stable_since) >= SETTLE_QUIET_PERIOD {
+                settled.push(path.clone());
+                return false;
+            }
+            true
+        });
+        settled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_of_events_settles_once() {
+        let start = Instant::now();
+        let path = PathBuf::from("campaign/autosave_2200.02.01.sav");
+        let stamp = (10, SystemTime::UNIX_EPOCH);
+        let mut pending = PendingSaves::default();
+
+        pending.touch(path.clone(), start);
+        pending.touch(path.clone(), start + Duration::from_millis(100));
+        assert!(pending
+            .take_settled(start + Duration::from_millis(200), |_| Some(stamp))
+            .is_empty());
+        assert!(pending
+            .take_settled(start + Duration::from_secs(1), |_| Some(stamp))
+            .is_empty());
+        assert_eq!(
+            pending.take_settled(start + Duration::from_secs(3), |_| Some(stamp)),
+            vec![path]
+        );
+        assert!(pending
+            .take_settled(start + Duration::from_secs(10), |_| Some(stamp))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_growing_file_is_not_settled() {
+        let start = Instant::now();
+        let path = PathBuf::from("campaign/autosave_2200.02.01.sav");
+        let mut pending = PendingSaves::default();
+
+        pending.touch(path.clone(), start);
+        for i in 0..5 {
+            let grown = (10 + i, SystemTime::UNIX_EPOCH);
+            assert!(pending
+                .take_settled(start + Duration::from_secs(i * 3), |_| Some(grown))
+                .is_empty());
+        }
+        assert_eq!(
+            pending.take_settled(start + Duration::from_secs(20), |_| Some((
+                14,
+                SystemTime::UNIX_EPOCH
+            ))),
+            vec![path]
+        );
+    }
+
+    #[test]
+    fn test_vanished_file_is_dropped() {
+        let start = Instant::now();
+        let mut pending = PendingSaves::default();
+
+        pending.touch(PathBuf::from("campaign/tmp.sav"), start);
+        assert!(pending
+            .take_settled(start + Duration::from_secs(5), |_| None)
+            .is_empty());
+        assert!(pending.saves.is_empty());
+    }
+}
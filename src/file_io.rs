@@ -1,49 +1,38 @@
-use std::{fs, io::Read};
+use std::{fs::File, io::Read};
 
-use zip::ZipArchive;
+use zip::{read::ZipFile, result::ZipError, ZipArchive};
+
+use crate::file::{binary::is_binary, error::SaveError};
 
 #[derive(Debug)]
 pub struct SaveFile {
     pub filename: String,
     pub game_id: String,
-    pub meta: String,
-    pub gamestate: String,
+    pub meta: SaveContent,
+    pub gamestate: SaveContent,
 }
 
-pub fn load_save_content<'a>(filename: &str) -> Result<SaveFile, &str> {
-    let save_path = std::path::Path::new(filename);
-
-    let err_msg = "Could not determine game ID";
-    let game_id = match save_path.parent() {
-        Some(p) => match p.file_name() {
-            Some(p) => match p.to_str() {
-                Some(s) => s.to_string(),
-                _ => return Err(err_msg),
-            },
-            _ => return Err(err_msg),
-        },
-        None => return Err(err_msg),
-    };
+/// An entry of the save archive, either plaintext or binary (ironman) encoded.
+#[derive(Debug)]
+pub enum SaveContent {
+    Text(String),
+    Binary(Vec<u8>),
+}
 
-    let zipfile = match std::fs::File::open(save_path) {
-        Ok(zf) => zf,
-        Err(_) => return Err("Failed to open file"),
-    };
+impl SaveContent {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            SaveContent::Text(s) => Some(s.as_str()),
+            SaveContent::Binary(_) => None,
+        }
+    }
+}
 
-    let mut archive = match ZipArchive::new(zipfile) {
-        Ok(a) => a,
-        Err(_) => return Err("Failed to read zip archive"),
-    };
+pub fn load_save_content(filename: &str) -> Result<SaveFile, SaveError> {
+    let (game_id, mut archive) = open_save(filename)?;
 
-    let meta = match read_file_from_archive(&mut archive, "meta") {
-        Ok(content) => content,
-        Err(e) => return Err(e),
-    };
-    let gamestate = match read_file_from_archive(&mut archive, "gamestate") {
-        Ok(content) => remove_tabs_and_newlines(content.as_str()),
-        Err(e) => return Err(e),
-    };
-    // fs::write("content", gamestate.clone().as_bytes());
+    let meta = read_file_from_archive(&mut archive, "meta")?;
+    let gamestate = read_file_from_archive(&mut archive, "gamestate")?;
     Ok(SaveFile {
         filename: String::from(filename),
         game_id,
@@ -52,25 +41,43 @@ pub fn load_save_content<'a>(filename: &str) -> Result<SaveFile, &str> {
     })
 }
 
-fn remove_tabs_and_newlines(input: &str) -> String {
-    let no_tabs = input.replace("\t", " ");
-    let no_tabs_or_newlines = no_tabs.replace("\n", " ");
-    no_tabs_or_newlines
+/// Opens the archive of a `.sav` file, its game id being the name of the
+/// campaign folder it is in.
+pub fn open_save(filename: &str) -> Result<(String, ZipArchive<File>), SaveError> {
+    let save_path = std::path::Path::new(filename);
+
+    let game_id = save_path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|p| p.to_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| SaveError::GameId(filename.to_string()))?;
+
+    let zipfile = File::open(save_path)?;
+    Ok((game_id, ZipArchive::new(zipfile)?))
+}
+
+/// The entry of the archive, to be read while it is decompressed.
+pub fn open_entry<'a>(
+    archive: &'a mut ZipArchive<File>,
+    fname: &'static str,
+) -> Result<ZipFile<'a>, SaveError> {
+    match archive.by_name(fname) {
+        Ok(file) => Ok(file),
+        Err(ZipError::FileNotFound) => Err(SaveError::MissingEntry(fname)),
+        Err(e) => Err(e.into()),
+    }
 }
 
 pub fn read_file_from_archive(
-    archive: &mut ZipArchive<std::fs::File>,
-    fname: &str,
-) -> Result<String, &'static str> {
-    let mut file_in_zip = match archive.by_name(fname) {
-        Ok(file) => file,
-        Err(_) => {
-            return Err("Could not locate file in zip archive");
-        }
-    };
-    let mut content = String::new();
-    file_in_zip
-        .read_to_string(&mut content)
-        .expect("Failed to read file contents from zip archive");
-    Ok(content)
+    archive: &mut ZipArchive<File>,
+    fname: &'static str,
+) -> Result<SaveContent, SaveError> {
+    let mut file_in_zip = open_entry(archive, fname)?;
+    let mut content = Vec::new();
+    file_in_zip.read_to_end(&mut content)?;
+    if is_binary(&content) {
+        return Ok(SaveContent::Binary(content));
+    }
+    Ok(SaveContent::Text(String::from_utf8(content)?))
 }
@@ -0,0 +1,305 @@
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, error, info};
+use once_cell::sync::Lazy;
+use prometheus::proto::MetricFamily;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    exporter::{configs::CONFIGS, exporter::gather_campaign},
+    models::game_date::GameDate,
+};
+
+/// History database configured in the `[history]` section of the `config.toml`.
+static HISTORY: Lazy<Option<Mutex<HistoryStore>>> = Lazy::new(|| {
+    let config = CONFIGS.lock().unwrap();
+    if !config.history.enabled {
+        return None;
+    }
+    match HistoryStore::open(Path::new(&config.history.database)) {
+        Ok(store) => {
+            info!("Recording history in {}", config.history.database);
+            Some(Mutex::new(store))
+        }
+        Err(e) => {
+            error!(
+                "Could not open the history database {}: {}",
+                config.history.database, e
+            );
+            None
+        }
+    }
+});
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+CREATE TABLE IF NOT EXISTS saves (
+    id INTEGER PRIMARY KEY,
+    game_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    ingested_at INTEGER NOT NULL,
+    UNIQUE (game_id, date, filename)
+);
+CREATE TABLE IF NOT EXISTS samples (
+    save_id INTEGER NOT NULL REFERENCES saves (id) ON DELETE CASCADE,
+    metric TEXT NOT NULL,
+    labels TEXT NOT NULL,
+    value REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS samples_by_metric ON samples (metric, save_id);
+";
+
+/// A single metric value as it was when a save was ingested.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HistorySample {
+    pub game_id: String,
+    pub date: String,
+    pub filename: String,
+    pub metric: String,
+    pub labels: Value,
+    pub value: f64,
+}
+
+/// Filters of a history lookup, every field being optional.
+///
+/// Dates are in-game dates (`2230.01.01`) and both bounds are inclusive.
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    pub game_id: Option<String>,
+    pub metric: Option<String>,
+    pub from: Option<GameDate>,
+    pub to: Option<GameDate>,
+}
+
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> rusqlite::Result<HistoryStore> {
+        HistoryStore::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(conn: Connection) -> rusqlite::Result<HistoryStore> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(HistoryStore { conn })
+    }
+
+    /// Stores every series of `families` for the given save. Ingesting the same
+    /// save again replaces what was stored for it.
+    ///
+    /// Dates are stored zero-padded (`2230.04.01`), so comparing them as text
+    /// in SQL orders them as dates.
+    pub fn record_save(
+        &mut self,
+        game_id: &str,
+        date: &str,
+        filename: &str,
+        families: &[MetricFamily],
+    ) -> rusqlite::Result<usize> {
+        let date = date
+            .parse::<GameDate>()
+            .map(|d| d.to_string())
+            .unwrap_or_else(|_| date.to_string());
+        let ingested_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM saves WHERE game_id = ?1 AND date = ?2 AND filename = ?3",
+            params![game_id, date, filename],
+        )?;
+        tx.execute(
+            "INSERT INTO saves (game_id, date, filename, ingested_at) VALUES (?1, ?2, ?3, ?4)",
+            params![game_id, date, filename, ingested_at],
+        )?;
+        let save_id = tx.last_insert_rowid();
+
+        let mut recorded = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO samples (save_id, metric, labels, value) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for family in families {
+                for metric in family.get_metric() {
+                    let labels: Map<String, Value> = metric
+                        .get_label()
+                        .iter()
+                        .filter(|l| l.get_name() != "save_name")
+                        .map(|l| (l.get_name().to_string(), Value::from(l.get_value())))
+                        .collect();
+                    let value = if metric.has_gauge() {
+                        metric.get_gauge().get_value()
+                    } else if metric.has_counter() {
+                        metric.get_counter().get_value()
+                    } else {
+                        continue;
+                    };
+                    insert.execute(params![
+                        save_id,
+                        family.get_name(),
+                        Value::Object(labels).to_string(),
+                        value
+                    ])?;
+                    recorded += 1;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(recorded)
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> rusqlite::Result<Vec<HistorySample>> {
+        let mut statement = self.conn.prepare(
+            "SELECT saves.game_id, saves.date, saves.filename, samples.metric, samples.labels, samples.value
+             FROM samples JOIN saves ON saves.id = samples.save_id
+             WHERE (?1 IS NULL OR saves.game_id = ?1)
+               AND (?2 IS NULL OR samples.metric = ?2)
+               AND saves.date BETWEEN IFNULL(?3, saves.date) AND IFNULL(?4, saves.date)
+             ORDER BY saves.game_id, saves.date, samples.metric",
+        )?;
+        let from = query.from.map(|d| d.to_string());
+        let to = query.to.map(|d| d.to_string());
+        let rows = statement.query_map(params![query.game_id, query.metric, from, to], |row| {
+            let labels: String = row.get(4)?;
+            Ok(HistorySample {
+                game_id: row.get(0)?,
+                date: row.get(1)?,
+                filename: row.get(2)?,
+                metric: row.get(3)?,
+                labels: serde_json::from_str(&labels).unwrap_or(Value::Null),
+                value: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Stores the current series of the `game_id` campaign, if the history is enabled.
+pub fn record_campaign(game_id: &str, date: &str, filename: &str) {
+    let Some(store) = HISTORY.as_ref() else {
+        return;
+    };
+    let families = gather_campaign(game_id);
+    match store
+        .lock()
+        .unwrap()
+        .record_save(game_id, date, filename, &families)
+    {
+        Ok(n) => debug!("Recorded {} samples of {} at {}", n, game_id, date),
+        Err(e) => error!("Could not record the history of {}: {}", game_id, e),
+    }
+}
+
+/// Looks up the recorded samples, `None` when the history is disabled.
+pub fn query_history(query: &HistoryQuery) -> Option<rusqlite::Result<Vec<HistorySample>>> {
+    HISTORY
+        .as_ref()
+        .map(|store| store.lock().unwrap().query(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{core::Collector, GaugeVec, Opts};
+
+    fn families(value: f64) -> Vec<MetricFamily> {
+        let gauge = GaugeVec::new(
+            Opts::new("stellaris_test", "test"),
+            &["save_name", "country"],
+        )
+        .unwrap();
+        gauge.with_label_values(&["campaign", "Earth"]).set(value);
+        gauge.collect()
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let mut store =
+            HistoryStore::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        store
+            .record_save(
+                "campaign",
+                "2200.02.01",
+                "autosave_2200.02.01.sav",
+                &families(1.0),
+            )
+            .unwrap();
+        store
+            .record_save(
+                "campaign",
+                "2200.03.01",
+                "autosave_2200.03.01.sav",
+                &families(2.0),
+            )
+            .unwrap();
+        // Ingesting a save twice keeps a single copy of it.
+        store
+            .record_save(
+                "campaign",
+                "2200.03.01",
+                "autosave_2200.03.01.sav",
+                &families(3.0),
+            )
+            .unwrap();
+
+        let all = store.query(&HistoryQuery::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|s| s.value).collect::<Vec<_>>(),
+            vec![1.0, 3.0]
+        );
+        assert_eq!(all[0].labels, serde_json::json!({"country": "Earth"}));
+
+        let later = store
+            .query(&HistoryQuery {
+                from: Some("2200.02.15".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(later.len(), 1);
+        assert_eq!(later[0].filename, "autosave_2200.03.01.sav");
+
+        // Bounds without leading zeros are compared as dates, not as text.
+        let march = store
+            .query(&HistoryQuery {
+                from: Some("2200.3.1".parse().unwrap()),
+                to: Some("2200.3.1".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(march.len(), 1);
+        assert_eq!(march[0].date, "2200.03.01");
+
+        // Dates are padded when recorded, so they sort and filter as dates.
+        store
+            .record_save(
+                "campaign",
+                "2200.4.1",
+                "autosave_2200.4.1.sav",
+                &families(4.0),
+            )
+            .unwrap();
+        let april = store
+            .query(&HistoryQuery {
+                from: Some("2200.03.15".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(april.len(), 1);
+        assert_eq!(april[0].date, "2200.04.01");
+        let all = store.query(&HistoryQuery::default()).unwrap();
+        assert_eq!(
+            all.iter().map(|s| s.value).collect::<Vec<_>>(),
+            vec![1.0, 3.0, 4.0]
+        );
+    }
+}
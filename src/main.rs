@@ -1,24 +1,24 @@
 mod api;
+mod cli;
 mod exporter;
 mod file;
 mod file_io;
+mod history;
 mod models;
 mod parser;
 mod singletons;
+mod writer;
 
-use std::{fmt::Display, fs, process::exit};
+use std::process::exit;
 
-use crate::{api::exp_api, exporter::renderers::render_name};
+use crate::api::exp_api;
 use actix_web::{middleware::Logger, App, HttpServer};
 use exporter::{
     configs::{read_configs, CONFIGS},
     exporter::register_metrics,
-    renderers::transform_input_name,
 };
-use file::{save_handler, watcher::spawn_file_watcher};
-use jomini::TextTape;
-use log::{error, trace};
-use once_cell::sync::Lazy;
+use file::watcher::spawn_file_watcher;
+use log::error;
 
 // ------
 // fn tests() {
@@ -76,17 +76,26 @@ async fn main() -> std::io::Result<()> {
     env_logger::init();
 
     register_metrics();
-    let mut config = CONFIGS.lock().unwrap();
-    *config = match read_configs() {
+    let config = match read_configs() {
         Ok(conf) => conf,
         Err(err) => {
             error!("Error while trying to read the config.toml: {:?}", err);
             exit(1);
         }
     };
+    let save_location = config.paths.save_location.clone();
+    let address = (config.api.ip.clone(), config.api.port);
+    // The lock must not outlive this statement, the renderers and the history
+    // store read the config lazily from other threads.
+    *CONFIGS.lock().unwrap() = config;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        exit(code);
+    }
 
     // r#"C:\Users\kella\OneDrive\Documentos\Paradox Interactive\Stellaris\save games"#.to_owned(),
-    spawn_file_watcher(config.paths.save_location.clone());
+    spawn_file_watcher(save_location);
 
     HttpServer::new(move || {
         let logger = Logger::default();
@@ -95,10 +104,11 @@ async fn main() -> std::io::Result<()> {
             .service(exp_api::index)
             .service(exp_api::parse)
             .service(exp_api::metrics)
+            .service(exp_api::history)
             .service(exp_api::test)
     })
     .workers(4)
-    .bind((config.api.ip.clone(), config.api.port.clone()))?
+    .bind(address)?
     .run()
     .await
 }
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, fmt, marker::PhantomData};
+
+use log::warn;
+use serde::{
+    de::{
+        value::{self, StrDeserializer},
+        IgnoredAny, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A section keyed by id, like `country={ 0={ ... } 1=none }`.
+///
+/// Destroyed entries are written as `none` and are skipped, empty `{}` entries
+/// keep their id with default values. An entry that does not match the model
+/// is skipped with a warning, without losing the rest of the section.
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct Entries<T>(pub BTreeMap<i64, T>);
+
+impl<T> Default for Entries<T> {
+    fn default() -> Self {
+        Entries(BTreeMap::new())
+    }
+}
+
+impl<T> Entries<T> {
+    pub fn get(&self, id: i64) -> Option<&T> {
+        self.0.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &T)> {
+        self.0.iter().map(|(id, entry)| (*id, entry))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.0.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Default> Deserialize<'de> for Entries<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de> + Default> Visitor<'de> for EntriesVisitor<T> {
+            type Value = Entries<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("entries keyed by id")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = BTreeMap::new();
+                while let Some(id) = map.next_key::<i64>()? {
+                    let Entry(entry) = map.next_value::<Entry<T>>().unwrap_or_else(|e| {
+                        warn!(
+                            "Entry {} does not match the model, it is skipped: {}",
+                            id, e
+                        );
+                        Entry(None)
+                    });
+                    if let Some(entry) = entry {
+                        entries.insert(id, entry);
+                    }
+                }
+                Ok(Entries(entries))
+            }
+
+            // An empty section is written as `{}`, which reads as an empty list.
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(Entries::default())
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+/// A single entry of a section, `None` when written as `none` or when a scalar
+/// does not fit the entry.
+struct Entry<T>(Option<T>);
+
+impl<'de, T: Deserialize<'de> + Default> Deserialize<'de> for Entry<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntryVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de> + Default> Visitor<'de> for EntryVisitor<T> {
+            type Value = Entry<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an entry or none")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                if v == "none" {
+                    return Ok(Entry(None));
+                }
+                // Scalar entries, like the slots of `modules={ 0="shipyard" }`.
+                let deserializer: StrDeserializer<'_, value::Error> = v.into_deserializer();
+                Ok(Entry(T::deserialize(deserializer).ok()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let deserializer = value::MapAccessDeserializer::new(map);
+                T::deserialize(deserializer).map(|entry| Entry(Some(entry)))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(Entry(Some(T::default())))
+            }
+        }
+
+        deserializer.deserialize_map(EntryVisitor(PhantomData))
+    }
+}
+
+/// Entries written either as a plain list (`{ { ... } { ... } }`) or keyed by
+/// id (`{ 0={ ... } 1=none }`), in the order of the save.
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct Listed<T>(pub Vec<T>);
+
+impl<T> Default for Listed<T> {
+    fn default() -> Self {
+        Listed(Vec::new())
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Default> Deserialize<'de> for Listed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ListedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de> + Default> Visitor<'de> for ListedVisitor<T> {
+            type Value = Listed<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a list or entries keyed by id")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = seq.next_element()? {
+                    entries.push(entry);
+                }
+                Ok(Listed(entries))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some((_, Entry(entry))) = map.next_entry::<IgnoredAny, Entry<T>>()? {
+                    entries.extend(entry);
+                }
+                Ok(Listed(entries))
+            }
+        }
+
+        deserializer.deserialize_any(ListedVisitor(PhantomData))
+    }
+}
+
+/// Strings written either once (`civics="a"`) or as a list (`civics={ "a" "b" }`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Strings(pub Vec<String>);
+
+impl Strings {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|s| s.as_str())
+    }
+}
+
+/// A single string is written back once, as the game does.
+impl Serialize for Strings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [single] => serializer.serialize_str(single),
+            strings => strings.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Strings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StringsVisitor;
+
+        impl<'de> Visitor<'de> for StringsVisitor {
+            type Value = Strings;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string or a list of strings")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Strings(vec![v.to_string()]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut strings = Vec::new();
+                while let Some(s) = seq.next_element()? {
+                    strings.push(s);
+                }
+                Ok(Strings(strings))
+            }
+        }
+
+        deserializer.deserialize_any(StringsVisitor)
+    }
+}
+
+/// A localised name, `{ key="..." variables={ ... } }` or `{ key="..." literal=yes }`.
+///
+/// Saves from before 3.4 write some names as a bare key, which reads as a name
+/// without variables.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LocalizedText {
+    pub key: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub literal: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<LocalizedVariable>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalizedVariable {
+    pub key: String,
+    pub value: Option<LocalizedText>,
+}
+
+impl<'de> Deserialize<'de> for LocalizedText {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LocalizedTextVisitor;
+
+        impl<'de> Visitor<'de> for LocalizedTextVisitor {
+            type Value = LocalizedText;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a localisation key or a localised name")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(LocalizedText {
+                    key: v.to_string(),
+                    ..Default::default()
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut text = LocalizedText::default();
+                while let Some(field) = map.next_key::<String>()? {
+                    match field.as_str() {
+                        "key" => text.key = map.next_value()?,
+                        "literal" => text.literal = map.next_value()?,
+                        "variables" => text.variables = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(text)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(LocalizedText::default())
+            }
+        }
+
+        deserializer.deserialize_any(LocalizedTextVisitor)
+    }
+}
+
+/// Map coordinates, with the system they are relative to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Coordinate {
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub origin: Option<i64>,
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use jomini::JominiDeserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::writer::repeated;
+
+use super::common::{LocalizedText, Strings};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Country {
+    pub name: Option<LocalizedText>,
+    pub military_power: Option<f64>,
+    pub tech_power: Option<f64>,
+    pub economy_power: Option<f64>,
+    pub victory_rank: Option<i64>,
+    pub victory_score: Option<f64>,
+    pub fleets_manager: Option<FleetsManager>,
+    pub modules: Option<CountryModules>,
+    pub budget: Option<Budget>,
+    pub tech_status: Option<TechStatus>,
+    pub ethos: Option<Ethos>,
+    pub government: Option<Government>,
+    pub traditions: Strings,
+    pub ascension_perks: Strings,
+    pub ruler: Option<i64>,
+    pub war_allies: Option<Vec<i64>>,
+    pub owned_planets: Option<Vec<i64>>,
+    pub controlled_planets: Option<Vec<i64>>,
+    pub surveyed: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FleetsManager {
+    pub owned_fleets: Vec<OwnedFleet>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OwnedFleet {
+    pub fleet: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CountryModules {
+    pub standard_economy_module: Option<EconomyModule>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EconomyModule {
+    /// The stockpile of each resource.
+    pub resources: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Budget {
+    pub current_month: Option<BudgetMonth>,
+    pub last_month: Option<BudgetMonth>,
+}
+
+/// Resources gained and spent in a month, by source (`country_base`,
+/// `planet_jobs`, ...) and then by resource.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetMonth {
+    pub income: BTreeMap<String, BTreeMap<String, f64>>,
+    pub expenses: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+/// Researched technologies are written as repeated `technology="..." level=N`
+/// pairs, so both lists line up by position.
+#[derive(Debug, Clone, Default, Serialize, JominiDeserialize)]
+pub struct TechStatus {
+    #[jomini(duplicated)]
+    #[serde(serialize_with = "repeated")]
+    pub technology: Vec<String>,
+    #[jomini(duplicated)]
+    #[serde(serialize_with = "repeated")]
+    pub level: Vec<i64>,
+    #[jomini(default)]
+    pub physics_queue: Vec<Research>,
+    #[jomini(default)]
+    pub society_queue: Vec<Research>,
+    #[jomini(default)]
+    pub engineering_queue: Vec<Research>,
+    pub alternatives: Option<ResearchAlternatives>,
+}
+
+impl TechStatus {
+    pub fn queue(&self, area: &str) -> &[Research] {
+        match area {
+            "physics" => &self.physics_queue,
+            "society" => &self.society_queue,
+            "engineering" => &self.engineering_queue,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Research {
+    pub technology: Option<String>,
+    pub progress: Option<f64>,
+}
+
+/// Technologies offered for the next pick of each area.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResearchAlternatives {
+    pub physics: Strings,
+    pub society: Strings,
+    pub engineering: Strings,
+}
+
+impl ResearchAlternatives {
+    pub fn area(&self, area: &str) -> Option<&Strings> {
+        match area {
+            "physics" => Some(&self.physics),
+            "society" => Some(&self.society),
+            "engineering" => Some(&self.engineering),
+            _ => None,
+        }
+    }
+}
+
+/// Ethics of a country or a pop, one `ethic="..."` per ethic.
+#[derive(Debug, Clone, Default, Serialize, JominiDeserialize)]
+pub struct Ethos {
+    #[jomini(duplicated)]
+    #[serde(serialize_with = "repeated")]
+    pub ethic: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Government {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub authority: Option<String>,
+    pub origin: Option<String>,
+    pub civics: Strings,
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use serde::{
+    de::{IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+use super::common::{Listed, LocalizedText};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Federation {
+    pub name: Option<LocalizedText>,
+    pub members: Vec<i64>,
+    /// The country holding the presidency.
+    pub leader: Option<i64>,
+    pub federation_progression: Option<FederationProgression>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FederationProgression {
+    pub federation_type: Option<String>,
+    pub levels: Option<f64>,
+    pub cohesion: Option<f64>,
+    pub experience: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GalacticCommunity {
+    pub members: Vec<i64>,
+    pub council: Vec<i64>,
+    /// The resolution currently voted on.
+    #[serde(deserialize_with = "voted_resolution")]
+    pub voting: Option<String>,
+    pub votes: Listed<Vote>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vote {
+    pub country: Option<i64>,
+    /// `yes`, `no` or `abstain`.
+    pub vote: Option<String>,
+    pub weight: Option<f64>,
+}
+
+/// A passed resolution of the top level `resolution` section.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resolution {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// `voting` is the key of the resolution, or an object holding it under
+/// `resolution` in newer saves.
+fn voted_resolution<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    struct VotingVisitor;
+
+    impl<'de> Visitor<'de> for VotingVisitor {
+        type Value = Option<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a resolution key or an object holding it")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(Some(v.to_string()))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut resolution = None;
+            while let Some(field) = map.next_key::<String>()? {
+                if field == "resolution" {
+                    resolution = Some(map.next_value()?);
+                } else {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            Ok(resolution)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(VotingVisitor)
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use super::common::{Coordinate, LocalizedText};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fleet {
+    pub name: Option<LocalizedText>,
+    pub owner: Option<i64>,
+    pub station: bool,
+    pub civilian: bool,
+    pub military_power: Option<f64>,
+    pub ships: Vec<i64>,
+    pub movement_manager: Option<MovementManager>,
+}
+
+impl Fleet {
+    /// Starbases and civilian fleets are told apart from the military ones by
+    /// their `station` and `civilian` flags.
+    pub fn fleet_type(&self) -> &'static str {
+        if self.station {
+            "station"
+        } else if self.civilian {
+            "civilian"
+        } else {
+            "military"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovementManager {
+    pub coordinate: Option<Coordinate>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ship {
+    pub fleet: Option<i64>,
+    pub ship_design: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShipDesign {
+    pub ship_size: Option<String>,
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::common::{Coordinate, Entries, Listed, LocalizedText};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GalacticObject {
+    pub name: Option<LocalizedText>,
+    pub coordinate: Option<Coordinate>,
+    pub starbases: Vec<i64>,
+    /// Saves from before 3.0 hold a single starbase.
+    pub starbase: Option<i64>,
+}
+
+impl GalacticObject {
+    pub fn starbase(&self) -> Option<i64> {
+        self.starbases.first().copied().or(self.starbase)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StarbaseManager {
+    pub starbases: Entries<Starbase>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Starbase {
+    pub level: Option<String>,
+    /// Older saves keep the owner on the starbase, newer ones only on the
+    /// fleet of the `station` ship.
+    pub owner: Option<i64>,
+    pub station: Option<i64>,
+    /// Written as slots, `0="shipyard" 1="anchorage"`.
+    pub modules: Listed<String>,
+    pub buildings: Listed<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Megastructure {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub owner: Option<i64>,
+}
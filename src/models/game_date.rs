@@ -0,0 +1,118 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer};
+
+/// First day of every Stellaris campaign.
+pub const START_YEAR: i64 = 2200;
+/// The game calendar has twelve months of thirty days each.
+pub const DAYS_PER_MONTH: i64 = 30;
+pub const DAYS_PER_YEAR: i64 = 12 * DAYS_PER_MONTH;
+
+/// An in-game date as written in saves (`2230.04.01`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GameDate {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+}
+
+impl GameDate {
+    /// Days elapsed since 2200.01.01.
+    pub fn days_since_start(&self) -> i64 {
+        (self.year - START_YEAR) * DAYS_PER_YEAR + (self.month - 1) * DAYS_PER_MONTH + self.day - 1
+    }
+
+    /// Finds a date in a save file name, e.g. `autosave_2230.04.01.sav`.
+    pub fn from_file_name(name: &str) -> Option<GameDate> {
+        let stem = name.strip_suffix(".sav").unwrap_or(name);
+        stem.rsplit(['_', ' '])
+            .next()
+            .and_then(|candidate| candidate.parse().ok())
+    }
+}
+
+impl FromStr for GameDate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        let [year, month, day] = parts[..] else {
+            return Err(format!("Invalid game date: {}", s));
+        };
+        let parse = |p: &str| {
+            p.parse::<i64>()
+                .map_err(|_| format!("Invalid game date: {}", s))
+        };
+        let date = GameDate {
+            year: parse(year)?,
+            month: parse(month)?,
+            day: parse(day)?,
+        };
+        if !(1..=12).contains(&date.month) || !(1..=DAYS_PER_MONTH).contains(&date.day) {
+            return Err(format!("Invalid game date: {}", s));
+        }
+        Ok(date)
+    }
+}
+
+/// Read from its text, like the bounds of a `/history` query.
+impl<'de> Deserialize<'de> for GameDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        date.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Display for GameDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}.{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game_date() {
+        let date: GameDate = "2340.09.01".parse().unwrap();
+        assert_eq!(
+            date,
+            GameDate {
+                year: 2340,
+                month: 9,
+                day: 1
+            }
+        );
+        assert_eq!(date.to_string(), "2340.09.01");
+        assert!("2340.13.01".parse::<GameDate>().is_err());
+        assert!("2340.09".parse::<GameDate>().is_err());
+        assert!("none".parse::<GameDate>().is_err());
+    }
+
+    #[test]
+    fn test_days_since_start() {
+        let days = |s: &str| s.parse::<GameDate>().unwrap().days_since_start();
+        assert_eq!(days("2200.01.01"), 0);
+        assert_eq!(days("2200.01.30"), 29);
+        assert_eq!(days("2200.02.01"), 30);
+        assert_eq!(days("2201.01.01"), 360);
+    }
+
+    #[test]
+    fn test_from_file_name() {
+        assert_eq!(
+            GameDate::from_file_name("autosave_2229.12.01.sav"),
+            Some(GameDate {
+                year: 2229,
+                month: 12,
+                day: 1
+            })
+        );
+        assert_eq!(
+            GameDate::from_file_name("2250.01.01.sav").map(|d| d.year),
+            Some(2250)
+        );
+        assert_eq!(GameDate::from_file_name("ironman.sav"), None);
+    }
+}
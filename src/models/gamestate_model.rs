@@ -1,108 +1,269 @@
-// Example code that deserializes and serializes the model.
-// extern crate serde;
-// #[macro_use]
-// extern crate serde_derive;
-// extern crate serde_json;
-//
-// use generated_module::Gamestate;
-//
-// fn main() {
-//     let json = r#"{"answer": 42}"#;
-//     let model: Gamestate = serde_json::from_str(&json).unwrap();
-// }
-
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use std::fmt;
+
+use log::warn;
+use serde::{
+    de::{
+        value::{MapAccessDeserializer, StrDeserializer},
+        DeserializeSeed, Error, MapAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize,
+};
+
+use super::{
+    common::{Entries, Listed},
+    country::Country,
+    diplomacy::{Federation, GalacticCommunity, Resolution},
+    fleet::{Fleet, Ship, ShipDesign},
+    galaxy::{GalacticObject, Megastructure, StarbaseManager},
+    leader::Leader,
+    market::Market,
+    planet::{Building, Planets, Pop, Species},
+    war::War,
+};
+
+/// The sections of the `gamestate` entry read by the extractors. Every other
+/// section and field of the save is skipped while deserializing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Gamestate {
-    pub slave_market_manager: Box<Option<serde_json::Value>>,
-    pub country: Box<Option<serde_json::Value>>,
-    pub leaders: Box<Option<serde_json::Value>>,
-    pub espionage_assets: Box<Option<serde_json::Value>>,
-    pub flags: Box<Option<serde_json::Value>>,
-    pub war: Box<Option<serde_json::Value>>,
-    pub orbital_line: Box<Option<serde_json::Value>>,
-    pub name: Box<Option<serde_json::Value>>,
-    pub random_name_database: Box<Option<serde_json::Value>>,
-    pub version: Box<Option<serde_json::Value>>,
-    pub saved_event_target: Box<Option<serde_json::Value>>,
-    pub used_species_names: Box<Option<serde_json::Value>>,
-    pub missile: Box<Option<serde_json::Value>>,
-    pub required_dlcs: Box<Option<serde_json::Value>>,
-    pub natural_wormholes: Box<Option<serde_json::Value>>,
-    pub resolution: Box<Option<serde_json::Value>>,
-    pub planets: Box<Option<serde_json::Value>>,
-    pub fleet: Box<Option<serde_json::Value>>,
-    pub army: Box<Option<serde_json::Value>>,
-    pub last_created_species_ref: Box<Option<serde_json::Value>>,
-    pub buildings: Box<Option<serde_json::Value>>,
-    pub used_symbols: Box<Option<serde_json::Value>>,
-    pub last_created_country: Box<Option<serde_json::Value>>,
-    pub galaxy: Box<Option<serde_json::Value>>,
-    pub used_species_portrait: Box<Option<serde_json::Value>>,
-    pub galactic_community: Box<Option<serde_json::Value>>,
-    pub strike_craft: Box<Option<serde_json::Value>>,
-    pub last_refugee_country: Box<Option<serde_json::Value>>,
-    pub fired_event_ids: Box<Option<serde_json::Value>>,
-    pub player: Box<Option<serde_json::Value>>,
-    pub truce: Box<Option<serde_json::Value>>,
-    pub last_event_id: Box<Option<serde_json::Value>>,
-    pub agreements: Box<Option<serde_json::Value>>,
-    pub last_created_army: Box<Option<serde_json::Value>>,
-    pub last_created_leader: Box<Option<serde_json::Value>>,
-    pub spy_networks: Box<Option<serde_json::Value>>,
-    pub saved_leaders: Box<Option<serde_json::Value>>,
-    pub galactic_object: Box<Option<serde_json::Value>>,
-    pub name_list: Box<Option<serde_json::Value>>,
-    pub last_created_system: Box<Option<serde_json::Value>>,
-    pub construction: Box<Option<serde_json::Value>>,
-    pub galaxy_radius: Box<Option<serde_json::Value>>,
-    pub debris: Box<Option<serde_json::Value>>,
-    pub trade_deal: Box<Option<serde_json::Value>>,
-    pub sectors: Box<Option<serde_json::Value>>,
-    pub espionage_operations: Box<Option<serde_json::Value>>,
-    pub first_contacts: Box<Option<serde_json::Value>>,
-    pub additional_crisis_strength: Box<Option<serde_json::Value>>,
-    pub last_created_pop: Box<Option<serde_json::Value>>,
-    pub date: Box<Option<serde_json::Value>>,
-    pub archaeological_sites: Box<Option<serde_json::Value>>,
-    pub random_log_day: Box<Option<serde_json::Value>>,
-    pub tick: Box<Option<serde_json::Value>>,
-    pub situations: Box<Option<serde_json::Value>>,
-    pub deposit: Box<Option<serde_json::Value>>,
-    pub random_seed: Box<Option<serde_json::Value>>,
-    pub megastructures: Box<Option<serde_json::Value>>,
-    pub pop: Box<Option<serde_json::Value>>,
-    pub last_notification_id: Box<Option<serde_json::Value>>,
-    pub bypasses: Box<Option<serde_json::Value>>,
-    pub trade_routes_manager: Box<Option<serde_json::Value>>,
-    pub last_created_fleet: Box<Option<serde_json::Value>>,
-    pub dummy_species: Box<Option<serde_json::Value>>,
-    pub global_ship_design: Box<Option<serde_json::Value>>,
-    pub ship_design: Box<Option<serde_json::Value>>,
-    pub last_killed_country_name: Box<Option<serde_json::Value>>,
-    pub version_control_revision: Box<Option<serde_json::Value>>,
-    pub open_player_event_selection_history: Box<Option<serde_json::Value>>,
-    pub used_color: Box<Option<serde_json::Value>>,
-    pub starbase_mgr: Box<Option<serde_json::Value>>,
-    pub random_count: Box<Option<serde_json::Value>>,
-    pub rim_galactic_objects: Box<Option<serde_json::Value>>,
-    pub clusters: Box<Option<serde_json::Value>>,
-    pub last_created_design: Box<Option<serde_json::Value>>,
-    pub last_diplo_action_id: Box<Option<serde_json::Value>>,
-    pub species_db: Box<Option<serde_json::Value>>,
-    pub message: Box<Option<serde_json::Value>>,
-    pub fleet_template: Box<Option<serde_json::Value>>,
-    pub ships: Box<Option<serde_json::Value>>,
-    pub last_created_ship: Box<Option<serde_json::Value>>,
-    pub federation: Box<Option<serde_json::Value>>,
-    pub ground_combat: Box<Option<serde_json::Value>>,
-    pub trade_routes: Box<Option<serde_json::Value>>,
-    pub system_initializer_counter: Box<Option<serde_json::Value>>,
-    pub ambient_object: Box<Option<serde_json::Value>>,
-    pub market: Box<Option<serde_json::Value>>,
-    pub last_created_ambient_object: Box<Option<serde_json::Value>>,
-    pub council_positions: Box<Option<serde_json::Value>>,
-    #[serde(default)]
-    _ignore: Option<serde_json::Value>,
+    pub version: Option<String>,
+    pub date: Option<String>,
+    pub tick: Option<i64>,
+    pub required_dlcs: Vec<String>,
+    pub country: Entries<Country>,
+    pub war: Entries<War>,
+    pub fleet: Entries<Fleet>,
+    pub ships: Entries<Ship>,
+    pub ship_design: Entries<ShipDesign>,
+    pub megastructures: Entries<Megastructure>,
+    pub planets: Planets,
+    pub buildings: Entries<Building>,
+    pub pop: Entries<Pop>,
+    pub leaders: Entries<Leader>,
+    pub species_db: Entries<Species>,
+    pub federation: Entries<Federation>,
+    pub galactic_community: Option<GalacticCommunity>,
+    pub resolution: Listed<Resolution>,
+    pub market: Option<Market>,
+    pub galactic_object: Entries<GalacticObject>,
+    pub starbase_mgr: StarbaseManager,
+}
+
+impl Gamestate {
+    /// Moves the `key` section out of `section`, a gamestate deserialized from
+    /// that section alone, as the save is read one section at a time.
+    pub fn take_section(&mut self, key: &str, section: Gamestate) {
+        match key {
+            "version" => self.version = section.version,
+            "date" => self.date = section.date,
+            "tick" => self.tick = section.tick,
+            "required_dlcs" => self.required_dlcs = section.required_dlcs,
+            "country" => self.country = section.country,
+            "war" => self.war = section.war,
+            "fleet" => self.fleet = section.fleet,
+            "ships" => self.ships = section.ships,
+            "ship_design" => self.ship_design = section.ship_design,
+            "megastructures" => self.megastructures = section.megastructures,
+            "planets" => self.planets = section.planets,
+            "buildings" => self.buildings = section.buildings,
+            "pop" => self.pop = section.pop,
+            "leaders" => self.leaders = section.leaders,
+            "species_db" => self.species_db = section.species_db,
+            "federation" => self.federation = section.federation,
+            "galactic_community" => self.galactic_community = section.galactic_community,
+            "resolution" => self.resolution = section.resolution,
+            "market" => self.market = section.market,
+            "galactic_object" => self.galactic_object = section.galactic_object,
+            "starbase_mgr" => self.starbase_mgr = section.starbase_mgr,
+            _ => {}
+        }
+    }
+}
+
+/// A gamestate deserialized one top level section at a time. A section that
+/// does not match the model is reported and left empty, so the metrics of the
+/// other sections are still exported.
+pub struct LenientGamestate(pub Gamestate);
+
+impl<'de> Deserialize<'de> for LenientGamestate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(LenientVisitor)
+            .map(LenientGamestate)
+    }
+}
+
+struct LenientVisitor;
+
+impl<'de> Visitor<'de> for LenientVisitor {
+    type Value = Gamestate;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a gamestate")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Gamestate, A::Error> {
+        let mut gamestate = Gamestate::default();
+        while let Some(key) = map.next_key::<String>()? {
+            if let Some(section) = map.next_value_seed(SectionSeed(&key))? {
+                gamestate.take_section(&key, section);
+            }
+        }
+        Ok(gamestate)
+    }
+}
+
+/// Deserializes the value of the `key` section as a gamestate holding only it.
+struct SectionSeed<'k>(&'k str);
+
+impl<'de> DeserializeSeed<'de> for SectionSeed<'_> {
+    type Value = Option<Gamestate>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let section = SectionAccess {
+            key: Some(self.0),
+            value: Some(deserializer),
+        };
+        match Gamestate::deserialize(MapAccessDeserializer::new(section)) {
+            Ok(section) => Ok(Some(section)),
+            Err(e) => {
+                warn!(
+                    "The {} section does not match the model, its metrics are skipped: {}",
+                    self.0, e
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// A map of the single `key=value` of a section.
+struct SectionAccess<'k, D> {
+    key: Option<&'k str>,
+    value: Option<D>,
+}
+
+impl<'de, D: Deserializer<'de>> MapAccess<'de> for SectionAccess<'_, D> {
+    type Error = D::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, D::Error> {
+        match self.key.take() {
+            Some(key) => seed.deserialize(StrDeserializer::new(key)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, D::Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(D::Error::custom("value of a section read twice")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jomini::{text::de::TextDeserializer, TextTape};
+
+    use super::*;
+
+    fn parse(text: &str) -> Gamestate {
+        let tape = TextTape::from_slice(text.as_bytes()).unwrap();
+        TextDeserializer::from_utf8_tape(&tape)
+            .deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_gamestate() {
+        let gm = parse(
+            r#"version="Gemini v3.8.4" date="2210.01.01" unknown_section={ a=1 }
+            country={
+                0={ name={ key="My Empire" literal=yes } military_power=12
+                    tech_status={ technology="tech_a" level=1 technology="tech_b" level=3 }
+                    ethos={ ethic="ethic_a" ethic="ethic_b" } traditions="tr_a" }
+                1=none
+                2={}
+            }
+            starbase_mgr={ starbases={ 4={ modules={ 0="shipyard" 1="anchorage" } owner=0 } } }
+            resolution={ { type="res_a" } { type="res_b" } }"#,
+        );
+        assert_eq!(gm.version.as_deref(), Some("Gemini v3.8.4"));
+        assert_eq!(gm.country.len(), 2);
+
+        let country = gm.country.get(0).unwrap();
+        let name = country.name.as_ref().unwrap();
+        assert_eq!(name.key, "My Empire");
+        assert!(name.literal);
+        assert_eq!(country.military_power, Some(12.0));
+        let tech = country.tech_status.as_ref().unwrap();
+        assert_eq!(tech.technology, vec!["tech_a", "tech_b"]);
+        assert_eq!(tech.level, vec![1, 3]);
+        assert_eq!(country.ethos.as_ref().unwrap().ethic.len(), 2);
+        assert_eq!(country.traditions.0, vec!["tr_a"]);
+        assert!(gm.country.get(2).unwrap().name.is_none());
+
+        let starbase = gm.starbase_mgr.starbases.get(4).unwrap();
+        assert_eq!(starbase.modules.0, vec!["shipyard", "anchorage"]);
+        assert_eq!(gm.resolution.0.len(), 2);
+    }
+
+    #[test]
+    fn test_deserialize_mismatched_section() {
+        let text = r#"date="2210.01.01"
+            country={ 0={ military_power=12 } }
+            leaders={ 1={ class="admiral" level={ 1 2 } } }"#;
+        let tape = TextTape::from_slice(text.as_bytes()).unwrap();
+        let LenientGamestate(gm) = TextDeserializer::from_utf8_tape(&tape)
+            .deserialize()
+            .unwrap();
+        assert_eq!(gm.date.as_deref(), Some("2210.01.01"));
+        assert_eq!(gm.country.len(), 1);
+        assert_eq!(gm.leaders.len(), 0);
+    }
+
+    #[test]
+    fn test_deserialize_mismatched_entry() {
+        let gm = parse(
+            r#"country={
+                0={ military_power=1 }
+                1={ military_power={ 1 2 } }
+                2={ military_power=3 }
+            }"#,
+        );
+        assert_eq!(gm.country.len(), 2);
+        assert_eq!(gm.country.get(0).unwrap().military_power, Some(1.0));
+        assert!(gm.country.get(1).is_none());
+        assert_eq!(gm.country.get(2).unwrap().military_power, Some(3.0));
+    }
+
+    #[test]
+    fn test_deserialize_leaders() {
+        let gm = parse(
+            r#"leaders={
+                1={ name={ first_name={ key="NAME_Jan" } second_name={ key="NAME_Kol" } }
+                    class="scientist" traits="leader_trait_curator" traits="leader_trait_expertise_biology"
+                    location={ assignment="research" area="physics" id=0 } }
+                2={ class="admiral" traits={ "leader_trait_aggressive" "leader_trait_trickster" } }
+            }"#,
+        );
+
+        let scientist = gm.leaders.get(1).unwrap();
+        assert_eq!(
+            scientist.traits().collect::<Vec<_>>(),
+            vec!["leader_trait_curator", "leader_trait_expertise_biology"]
+        );
+        let name = scientist.name.as_ref().unwrap();
+        assert_eq!(name.first_name.as_ref().unwrap().key, "NAME_Jan");
+        assert_eq!(name.second_name.as_ref().unwrap().key, "NAME_Kol");
+        let location = scientist.location.as_ref().unwrap();
+        assert_eq!(location.assignment.as_deref(), Some("research"));
+
+        let admiral = gm.leaders.get(2).unwrap();
+        assert_eq!(admiral.traits().count(), 2);
+        assert!(admiral.location.is_none());
+    }
 }
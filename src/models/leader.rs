@@ -0,0 +1,47 @@
+use jomini::JominiDeserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::writer::repeated;
+
+use super::common::{LocalizedText, Strings};
+
+#[derive(Debug, Clone, Default, Serialize, JominiDeserialize)]
+pub struct Leader {
+    pub name: Option<LeaderName>,
+    pub country: Option<i64>,
+    pub class: Option<String>,
+    pub level: Option<i64>,
+    pub age: Option<i64>,
+    /// Written once per trait, or as a single list.
+    #[jomini(duplicated)]
+    #[serde(serialize_with = "repeated")]
+    pub traits: Vec<Strings>,
+    pub date_added: Option<String>,
+    pub date: Option<String>,
+    pub location: Option<LeaderLocation>,
+}
+
+impl Leader {
+    pub fn traits(&self) -> impl Iterator<Item = &str> {
+        self.traits.iter().flat_map(|traits| traits.iter())
+    }
+}
+
+/// Saves from 3.8 on write a localised `full_names`, older ones the keys of
+/// the first and last name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaderName {
+    pub full_names: Option<LocalizedText>,
+    pub first_name: Option<LocalizedText>,
+    pub second_name: Option<LocalizedText>,
+}
+
+/// What the leader is assigned to, `assignment` being `none` while idle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaderLocation {
+    pub assignment: Option<String>,
+    pub area: Option<String>,
+    pub id: Option<i64>,
+}
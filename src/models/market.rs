@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{
+    de::{IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+/// The galactic market. Its lists are ordered by resource, see
+/// `STRATEGIC_RESOURCES` in the game files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Market {
+    pub fluctuations: Vec<f64>,
+    /// Countries with access to the market, in the order of the rows below.
+    pub id: Vec<i64>,
+    pub resources_bought: MarketRows,
+    pub resources_sold: MarketRows,
+    pub internal_market_fluctuations: Option<InternalMarket>,
+}
+
+/// Per-country rows of amounts by resource, written as a plain list of rows
+/// or under one or several `amount` keys.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct MarketRows(pub Vec<Vec<f64>>);
+
+impl<'de> Deserialize<'de> for MarketRows {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RowsVisitor;
+
+        impl<'de> Visitor<'de> for RowsVisitor {
+            type Value = MarketRows;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("market rows")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut rows = Vec::new();
+                while let Some(row) = seq.next_element()? {
+                    rows.push(row);
+                }
+                Ok(MarketRows(rows))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut rows = Vec::new();
+                while let Some(field) = map.next_key::<String>()? {
+                    if field != "amount" {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    let cells: Vec<Cell> = map.next_value()?;
+                    if cells.iter().all(|cell| matches!(cell, Cell::Amount(_))) {
+                        rows.push(cells.into_iter().map(Cell::into_amount).collect());
+                    } else {
+                        rows.extend(cells.into_iter().map(Cell::into_row));
+                    }
+                }
+                Ok(MarketRows(rows))
+            }
+        }
+
+        deserializer.deserialize_any(RowsVisitor)
+    }
+}
+
+/// An element of `amount`, which holds a single row or every row.
+enum Cell {
+    Amount(f64),
+    Row(Vec<f64>),
+}
+
+impl Cell {
+    fn into_amount(self) -> f64 {
+        match self {
+            Cell::Amount(amount) => amount,
+            Cell::Row(_) => 0.0,
+        }
+    }
+
+    fn into_row(self) -> Vec<f64> {
+        match self {
+            Cell::Amount(amount) => vec![amount],
+            Cell::Row(row) => row,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CellVisitor;
+
+        impl<'de> Visitor<'de> for CellVisitor {
+            type Value = Cell;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an amount or a row of amounts")
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(Cell::Amount(v as f64))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(Cell::Amount(v as f64))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(Cell::Amount(v))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map(Cell::Amount).map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut row = Vec::new();
+                while let Some(amount) = seq.next_element()? {
+                    row.push(amount);
+                }
+                Ok(Cell::Row(row))
+            }
+        }
+
+        deserializer.deserialize_any(CellVisitor)
+    }
+}
+
+/// Price fluctuations of the internal markets, by country. Written either as
+/// one `country=N resources={ ... }` pair per country or as two lists.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InternalMarket {
+    pub country: Vec<i64>,
+    pub resources: Vec<BTreeMap<String, f64>>,
+}
+
+impl<'de> Deserialize<'de> for InternalMarket {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct InternalMarketVisitor;
+
+        impl<'de> Visitor<'de> for InternalMarketVisitor {
+            type Value = InternalMarket;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("internal market fluctuations")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut market = InternalMarket::default();
+                while let Some(field) = map.next_key::<String>()? {
+                    match field.as_str() {
+                        "country" => match map.next_value()? {
+                            Cell::Amount(id) => market.country.push(id as i64),
+                            Cell::Row(ids) => {
+                                market.country.extend(ids.iter().map(|id| *id as i64))
+                            }
+                        },
+                        "resources" => match map.next_value()? {
+                            Fluctuations::One(row) => market.resources.push(row),
+                            Fluctuations::Many(rows) => market.resources.extend(rows),
+                        },
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                Ok(market)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(InternalMarket::default())
+            }
+        }
+
+        deserializer.deserialize_map(InternalMarketVisitor)
+    }
+}
+
+/// The fluctuations of one country, or of every country.
+enum Fluctuations {
+    One(BTreeMap<String, f64>),
+    Many(Vec<BTreeMap<String, f64>>),
+}
+
+impl<'de> Deserialize<'de> for Fluctuations {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FluctuationsVisitor;
+
+        impl<'de> Visitor<'de> for FluctuationsVisitor {
+            type Value = Fluctuations;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("fluctuations by resource")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+                BTreeMap::deserialize(deserializer).map(Fluctuations::One)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut rows = Vec::new();
+                while let Some(row) = seq.next_element()? {
+                    rows.push(row);
+                }
+                Ok(Fluctuations::Many(rows))
+            }
+        }
+
+        deserializer.deserialize_any(FluctuationsVisitor)
+    }
+}
@@ -1 +1,11 @@
+pub mod common;
+pub mod country;
+pub mod diplomacy;
+pub mod fleet;
+pub mod galaxy;
+pub mod game_date;
 pub mod gamestate_model;
+pub mod leader;
+pub mod market;
+pub mod planet;
+pub mod war;
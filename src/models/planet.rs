@@ -0,0 +1,88 @@
+use jomini::JominiDeserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::writer::repeated;
+
+use super::{
+    common::{Entries, LocalizedText, Strings},
+    country::Ethos,
+};
+
+/// The `planets` section, which nests every planet under `planet`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Planets {
+    pub planet: Entries<Planet>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JominiDeserialize)]
+pub struct Planet {
+    pub name: Option<LocalizedText>,
+    pub owner: Option<i64>,
+    pub planet_class: Option<String>,
+    pub designation: Option<String>,
+    pub final_designation: Option<String>,
+    pub planet_size: Option<f64>,
+    pub stability: Option<f64>,
+    pub amenities: Option<f64>,
+    pub amenities_usage: Option<f64>,
+    pub free_amenities: Option<f64>,
+    pub crime: Option<f64>,
+    pub total_housing: Option<f64>,
+    pub free_housing: Option<f64>,
+    pub free_jobs: Option<f64>,
+    pub employable_pops: Option<f64>,
+    pub num_sapient_pops: Option<f64>,
+    #[jomini(duplicated)]
+    #[serde(serialize_with = "repeated")]
+    pub district: Vec<Strings>,
+    #[jomini(default)]
+    pub buildings_cache: Vec<i64>,
+}
+
+impl Planet {
+    /// The stats exported by `stellaris_planet_stats`, by stat name.
+    pub fn stats(&self) -> [(&'static str, Option<f64>); 11] {
+        [
+            ("size", self.planet_size),
+            ("stability", self.stability),
+            ("amenities", self.amenities),
+            ("amenities_usage", self.amenities_usage),
+            ("free_amenities", self.free_amenities),
+            ("crime", self.crime),
+            ("housing", self.total_housing),
+            ("free_housing", self.free_housing),
+            ("free_jobs", self.free_jobs),
+            ("employable_pops", self.employable_pops),
+            ("pops", self.num_sapient_pops),
+        ]
+    }
+
+    pub fn districts(&self) -> impl Iterator<Item = &str> {
+        self.district.iter().flat_map(|districts| districts.iter())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Building {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pop {
+    pub species: Option<i64>,
+    pub planet: Option<i64>,
+    pub job: Option<String>,
+    pub category: Option<String>,
+    pub ethos: Option<Ethos>,
+    pub happiness: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Species {
+    pub name: Option<LocalizedText>,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use super::common::LocalizedText;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct War {
+    pub name: Option<LocalizedText>,
+    pub start_date: Option<String>,
+    pub attackers: Vec<WarParticipant>,
+    pub defenders: Vec<WarParticipant>,
+    pub attacker_war_goal: Option<WarGoal>,
+    pub defender_war_goal: Option<WarGoal>,
+    pub attacker_war_exhaustion: Option<f64>,
+    pub defender_war_exhaustion: Option<f64>,
+    pub battles: Vec<Battle>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarParticipant {
+    pub country: Option<i64>,
+    /// `primary` for the main belligerents, `overlord`, `defensive`, ... for the called ones.
+    pub call_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarGoal {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Battle {
+    pub attackers: Vec<i64>,
+    pub defenders: Vec<i64>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub system: Option<i64>,
+    pub attacker_victory: Option<bool>,
+    pub attacker_losses: Option<f64>,
+    pub defender_losses: Option<f64>,
+}
@@ -1,49 +1,407 @@
+use std::borrow::Cow;
 use std::cmp::min;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 use std::time::Instant;
 
-use log::{error, trace};
+use log::{debug, trace};
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while, take_while1};
-use nom::bytes::streaming::escaped;
-use nom::character::complete::{char, multispace0, multispace1, none_of, one_of};
-use nom::combinator::{map, map_res, recognize};
-use nom::error::context;
-use nom::multi::{separated_list0, separated_list1};
-use nom::number::complete::double;
-use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::bytes::complete::{tag, take_till, take_while1};
+use nom::character::complete::{char, multispace1};
+use nom::combinator::{cut, map, recognize, value, verify};
+use nom::error::{
+    context, ContextError, Error, ErrorKind, ParseError as NomParseError, VerboseError,
+    VerboseErrorKind,
+};
+use nom::multi::{many0, many0_count};
+use nom::sequence::{pair, preceded, terminated};
 use nom::IResult;
 
+use serde::ser::{SerializeMap, Serializer};
 use serde::Serialize;
 
-use crate::file_io::SaveFile;
+use crate::{file::error::SaveError, file_io::SaveFile};
 
-#[derive(Serialize, Debug, PartialEq)]
-#[serde(untagged)]
+/// A value of a Clausewitz file.
+///
+/// Blocks keep their entries in the order of the file, repeated keys included,
+/// so `x=1 x=2` and `x={ 1 2 }` stay distinct.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value<'a> {
-    None,
+    /// Unquoted scalars: identifiers, `yes`/`no`, dates, `@variables` and
+    /// `@[ inline math ]`.
     Str(&'a str),
+    /// Quoted strings, with `\"` and `\\` unescaped.
+    Quoted(Cow<'a, str>),
     Int(i64),
     Float(f64),
+    /// Colour literals, `rgb { 255 128 0 }` or `hsv { 0.5 0.2 0.8 }`.
+    Color {
+        space: &'a str,
+        channels: Vec<Value<'a>>,
+    },
+    /// Blocks of bare values, `{ 1 2 3 }`. Empty blocks read as an empty list.
     List(Vec<Value<'a>>),
-    Map(HashMap<&'a str, Value<'a>>),
+    /// Blocks of fields, `{ a=1 b>2 }`.
+    Map(Vec<Field<'a>>),
+    /// Blocks mixing both, `{ 1 2 a=3 }`.
+    Mixed(Vec<Item<'a>>),
 }
 
-impl Display for Value<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+/// A `key <operator> value` entry of a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field<'a> {
+    pub key: Key<'a>,
+    pub op: Operator,
+    pub value: Value<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key<'a> {
+    Bare(&'a str),
+    Quoted(Cow<'a, str>),
+}
+
+/// An entry of a mixed block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<'a> {
+    Value(Value<'a>),
+    Field(Field<'a>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `=`
+    Equal,
+    /// `==`
+    Exact,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    LessThan,
+    /// `<=`
+    LessThanEqual,
+    /// `>`
+    GreaterThan,
+    /// `>=`
+    GreaterThanEqual,
+    /// `?=`, true when the right hand side exists and is equal.
+    Exists,
+}
+
+impl Operator {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::Exact => "==",
+            Operator::NotEqual => "!=",
+            Operator::LessThan => "<",
+            Operator::LessThanEqual => "<=",
+            Operator::GreaterThan => ">",
+            Operator::GreaterThanEqual => ">=",
+            Operator::Exists => "?=",
+        }
+    }
+}
+
+impl Key<'_> {
+    pub fn as_str(&self) -> &str {
         match self {
-            Value::None => write!(f, "none"),
-            Value::Str(s) => write!(f, "{}", s),
-            Value::Int(n) => write!(f, "{}", n),
-            Value::Float(x) => write!(f, "{}", x),
-            Value::List(vec) => write!(f, "[{:?}]", vec),
-            Value::Map(hm) => write!(f, "{:?}", hm),
+            Key::Bare(key) => key,
+            Key::Quoted(key) => key,
         }
     }
 }
 
+impl<'a> Value<'a> {
+    /// The fields of a map or of a mixed block, in order.
+    pub fn fields(&self) -> impl Iterator<Item = &Field<'a>> {
+        let (fields, items): (&[Field<'a>], &[Item<'a>]) = match self {
+            Value::Map(fields) => (fields, &[]),
+            Value::Mixed(items) => (&[], items),
+            _ => (&[], &[]),
+        };
+        fields
+            .iter()
+            .chain(items.iter().filter_map(|item| match item {
+                Item::Field(field) => Some(field),
+                Item::Value(_) => None,
+            }))
+    }
+
+    /// The value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        self.fields()
+            .find(|field| field.key.as_str() == key)
+            .map(|field| &field.value)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            Value::Quoted(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+/// Serializes to the same JSON as jomini with `DuplicateKeyMode::Group`:
+/// repeated keys are grouped into an array, and fields compared with another
+/// operator than `=` are written as `{ "<": value }`.
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::Quoted(s) => serializer.serialize_str(s),
+            Value::Int(n) => serializer.serialize_i64(*n),
+            Value::Float(x) => serializer.serialize_f64(*x),
+            Value::Color { space, channels } => {
+                let mut color = serializer.serialize_map(Some(1))?;
+                color.serialize_entry(space, channels)?;
+                color.end()
+            }
+            Value::List(values) => serializer.collect_seq(values),
+            Value::Map(fields) => serialize_fields(fields, serializer),
+            Value::Mixed(items) => serializer.collect_seq(items),
+        }
+    }
+}
+
+impl Serialize for Item<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Item::Value(value) => value.serialize(serializer),
+            Item::Field(field) => {
+                let mut entry = serializer.serialize_map(Some(1))?;
+                entry.serialize_entry(field.key.as_str(), &FieldValue(field))?;
+                entry.end()
+            }
+        }
+    }
+}
+
+struct FieldValue<'f, 'a>(&'f Field<'a>);
+
+impl Serialize for FieldValue<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.op {
+            Operator::Equal => self.0.value.serialize(serializer),
+            op => {
+                let mut comparison = serializer.serialize_map(Some(1))?;
+                comparison.serialize_entry(op.symbol(), &self.0.value)?;
+                comparison.end()
+            }
+        }
+    }
+}
+
+fn serialize_fields<S: Serializer>(fields: &[Field], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut groups: Vec<(&str, Vec<FieldValue>)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for field in fields {
+        let key = field.key.as_str();
+        match positions.get(key) {
+            Some(&position) => groups[position].1.push(FieldValue(field)),
+            None => {
+                positions.insert(key, groups.len());
+                groups.push((key, vec![FieldValue(field)]));
+            }
+        }
+    }
+
+    let mut map = serializer.serialize_map(Some(groups.len()))?;
+    for (key, group) in &groups {
+        match group.as_slice() {
+            [single] => map.serialize_entry(key, single)?,
+            _ => map.serialize_entry(key, group)?,
+        }
+    }
+    map.end()
+}
+
+/// Where and why a file could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset of the error in the input.
+    pub offset: usize,
+    /// 1-based line of the error.
+    pub line: usize,
+    /// 1-based column of the error, in characters.
+    pub column: usize,
+    /// What went wrong, like `expected '}', found end of input`.
+    pub message: String,
+    /// The line of the error, shortened to a few dozen characters around it.
+    pub snippet: String,
+    /// Position of the error in the snippet, in characters.
+    pub snippet_column: usize,
+    /// What was being read when the error happened, innermost first.
+    pub context: Vec<ErrorContext>,
+}
+
+/// A `context` label of the parser and where it started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorContext {
+    pub label: &'static str,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Characters of the snippet kept on each side of the error.
+const SNIPPET_RADIUS: usize = 40;
+
+impl ParseError {
+    fn from_verbose(input: &str, error: VerboseError<&str>) -> ParseError {
+        let lines = Lines::new(input);
+        let offset_of = |at: &str| input.len() - at.len();
+
+        let (at, kind) = error
+            .errors
+            .iter()
+            .find(|(_, kind)| !matches!(kind, VerboseErrorKind::Context(_)))
+            .or(error.errors.first())
+            .map_or((&input[input.len()..], None), |(at, kind)| {
+                (*at, Some(kind))
+            });
+        let found = match at.chars().next() {
+            Some(c) => format!("{:?}", c),
+            None => "end of input".to_string(),
+        };
+        let message = match kind {
+            Some(VerboseErrorKind::Char(expected)) => {
+                format!("expected {:?}, found {}", expected, found)
+            }
+            _ => format!("unexpected {}", found),
+        };
+
+        let offset = offset_of(at);
+        let (line, column) = lines.position(offset);
+        let (snippet, snippet_column) = lines.snippet(offset);
+        let context = error
+            .errors
+            .iter()
+            .filter_map(|(at, kind)| match kind {
+                VerboseErrorKind::Context(label) => {
+                    let (line, column) = lines.position(offset_of(at));
+                    Some(ErrorContext {
+                        label,
+                        line,
+                        column,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        ParseError {
+            offset,
+            line,
+            column,
+            message,
+            snippet,
+            snippet_column,
+            context,
+        }
+    }
+
+    /// Moves an error found in a piece of a bigger input, like a section of a
+    /// streamed gamestate, to the position of that piece in the whole input.
+    pub fn starting_at(mut self, offset: usize, line: usize, column: usize) -> ParseError {
+        let shift = |at_line: &mut usize, at_column: &mut usize| {
+            if *at_line == 1 {
+                *at_column += column - 1;
+            }
+            *at_line += line - 1;
+        };
+        self.offset += offset;
+        shift(&mut self.line, &mut self.column);
+        for context in &mut self.context {
+            shift(&mut context.line, &mut context.column);
+        }
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )?;
+        write!(
+            f,
+            "\n    {}\n    {}^",
+            self.snippet,
+            " ".repeat(self.snippet_column)
+        )?;
+        for context in &self.context {
+            write!(
+                f,
+                "\n  in {} at line {}, column {}",
+                context.label, context.line, context.column
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Start offsets of the lines of an input, to turn offsets into positions.
+struct Lines<'a> {
+    input: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(input: &'a str) -> Lines<'a> {
+        let starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Lines { input, starts }
+    }
+
+    fn line_start(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|start| *start <= offset);
+        (line, self.starts[line - 1])
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let (line, start) = self.line_start(offset);
+        (line, self.input[start..offset].chars().count() + 1)
+    }
+
+    fn snippet(&self, offset: usize) -> (String, usize) {
+        let (_, line_start) = self.line_start(offset);
+        let line_end = self.input[offset..]
+            .find('\n')
+            .map_or(self.input.len(), |i| offset + i);
+        let start = self.input[line_start..offset]
+            .char_indices()
+            .rev()
+            .nth(SNIPPET_RADIUS - 1)
+            .map_or(line_start, |(i, _)| line_start + i);
+        let end = self.input[offset..line_end]
+            .char_indices()
+            .nth(SNIPPET_RADIUS)
+            .map_or(line_end, |(i, _)| offset + i);
+        // Tabs are printed as a single space so the caret lines up.
+        let snippet = self.input[start..end]
+            .trim_end_matches('\r')
+            .replace('\t', " ");
+        (snippet, self.input[start..offset].chars().count())
+    }
+}
+
 pub struct ParsedSaveFile<'a> {
     pub gamestate: Value<'a>,
     pub meta: Value<'a>,
@@ -51,17 +409,22 @@ pub struct ParsedSaveFile<'a> {
     pub parsed_time: Instant,
 }
 
-pub fn parse_save<'a>(save_file: &'a SaveFile) -> Result<ParsedSaveFile<'a>, &'static str> {
-    let meta_contents = save_file.meta.as_str();
-    let meta = match parse_file(meta_contents) {
-        Ok(result) => result,
-        Err(_) => return Err("Failed to parse save metadata"),
-    };
-
-    let gamestate = match parse_file((&save_file.gamestate).as_str()) {
-        Ok(result) => result,
-        Err(_) => return Err("Failed to parse save gamestate"),
+pub fn parse_save(save_file: &SaveFile) -> Result<ParsedSaveFile<'_>, SaveError> {
+    let (Some(meta_contents), Some(gamestate_contents)) =
+        (save_file.meta.as_text(), save_file.gamestate.as_text())
+    else {
+        return Err(SaveError::Parse(
+            "Binary saves are not supported by this parser".to_string(),
+        ));
     };
+    let meta = parse_file(meta_contents).map_err(|error| SaveError::Syntax {
+        entry: "meta",
+        error,
+    })?;
+    let gamestate = parse_file(gamestate_contents).map_err(|error| SaveError::Syntax {
+        entry: "gamestate",
+        error,
+    })?;
     Ok(ParsedSaveFile {
         gamestate,
         meta,
@@ -70,172 +433,261 @@ pub fn parse_save<'a>(save_file: &'a SaveFile) -> Result<ParsedSaveFile<'a>, &'s
     })
 }
 
-pub fn parse_file<'a>(input: &'a str) -> Result<Value<'a>, &str> {
-    match parse_map_inner(input) {
-        Ok((remainder, hm)) => {
-            trace!("Remainder length: {:?}", remainder.len());
-            if remainder.chars().all(char::is_whitespace) {
-                Ok(Value::Map(hm))
-            } else {
-                error!("Remainder is not empty: {:?}", remainder.len());
-                Err(remainder)
-            }
+/// Parses the content of a save entry, a game file or a mod file.
+///
+/// The input is parsed a second time with verbose errors when it fails, so
+/// valid files do not pay for the error reporting.
+pub fn parse_file(input: &str) -> Result<Value<'_>, ParseError> {
+    let body = input.strip_prefix('\u{feff}').unwrap_or(input);
+    match parse_document::<Error<&str>>(body) {
+        Ok((_, value)) => Ok(value),
+        Err(_) => {
+            let error = match parse_document::<VerboseError<&str>>(body) {
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e,
+                _ => VerboseError::from_error_kind(body, ErrorKind::Fail),
+            };
+            let error = ParseError::from_verbose(input, error);
+            debug!(
+                "Parsing failed at line {}, column {}: {}",
+                error.line, error.column, error.message
+            );
+            Err(error)
         }
-        _ => Err("Parsing failed"),
     }
 }
 
-fn parse_none(input: &str) -> IResult<&str, Value> {
-    trace!("Running Parsing Value: {}", input);
-    let (input, _) = tag("none")(input)?;
-    Ok((input, Value::None))
+fn parse_document<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Value<'a>, E> {
+    let (remainder, items) = parse_items(input)?;
+    let (remainder, _) = ws(remainder)?;
+    trace!("Remainder length: {:?}", remainder.len());
+    if !remainder.is_empty() {
+        return Err(nom::Err::Error(E::from_error_kind(
+            remainder,
+            ErrorKind::Eof,
+        )));
+    }
+    if items.is_empty() {
+        Ok((remainder, Value::Map(Vec::new())))
+    } else {
+        Ok((remainder, block(items)))
+    }
 }
 
-fn parse_value(input: &str) -> IResult<&str, Value> {
-    // print!("Parsing next value from: ");
-    // debug_str(input);
-    // trace!("Running Parsing Value: {}", input.len());
-    alt((
-        context("date", map(parse_date_str, Value::Str)),
-        context("int", map(parse_int, Value::Int)),
-        context("float", map(parse_float, Value::Float)),
-        context("str", map(parse_str, Value::Str)),
-        context("unquoted_str", map(parse_unquoted_str, Value::Str)),
-        context("list", map(parse_list, Value::List)),
-        context("map", map(parse_map, Value::Map)),
-        context("none", parse_none),
-    ))(input)
+/// Whitespace and `#` comments.
+fn ws<'a, E: NomParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
+    value(
+        (),
+        many0_count(alt((
+            multispace1,
+            recognize(pair(char('#'), take_till(|c| c == '\n'))),
+        ))),
+    )(input)
 }
 
-fn parse_list(input: &str) -> IResult<&str, Vec<Value>> {
-    delimited(
-        terminated(tag("{"), multispace0),
-        parse_list_inner,
-        preceded(multispace0, tag("}")),
-    )(input)
+fn parse_items<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Vec<Item<'a>>, E> {
+    many0(preceded(ws, parse_item))(input)
 }
 
-fn parse_list_inner(input: &str) -> IResult<&str, Vec<Value>> {
-    separated_list0(multispace1, parse_value)(input)
+fn parse_item<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Item<'a>, E> {
+    alt((map(parse_field, Item::Field), map(parse_value, Item::Value)))(input)
 }
 
-fn parse_map(input: &str) -> IResult<&str, HashMap<&str, Value>> {
-    delimited(
-        preceded(multispace0, tag("{")),
-        parse_map_inner,
-        preceded(multispace0, tag("}")),
-    )(input)
+fn parse_field<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Field<'a>, E> {
+    let (rest, key) = parse_key(input)?;
+    let (rest, op) = preceded(ws, parse_operator)(rest)?;
+    // Past the operator, the value is mandatory.
+    let (rest, value) = cut(alt((
+        // key can be missing in some cases, see test_skipped_key_in_mapping.
+        // in this case, discard the second key
+        preceded(
+            pair(preceded(ws, parse_token), preceded(ws, char('='))),
+            parse_value,
+        ),
+        parse_value,
+    )))(rest)
+    .map_err(|e| e.map(|e| E::add_context(input, "field", e)))?;
+    Ok((rest, Field { key, op, value }))
 }
 
-fn parse_map_inner(input: &str) -> IResult<&str, HashMap<&str, Value>> {
-    let mut handled_nested_list_keys: HashSet<&str> = HashSet::new();
-    match parse_map_kv_list(input) {
-        Ok((remainder, kv_list)) => {
-            let mut hm = HashMap::new();
-            for (key, value) in kv_list {
-                let old_value = hm.remove(key);
-                match old_value {
-                    None => {
-                        hm.insert(key, value);
-                    }
-                    Some(Value::List(mut vec)) => {
-                        let value_to_insert = match (value, handled_nested_list_keys.contains(key))
-                        {
-                            (Value::List(new_vec), false) => {
-                                handled_nested_list_keys.insert(key);
-                                Value::List(Vec::from([Value::List(vec), Value::List(new_vec)]))
-                            }
-                            (value, _) => {
-                                vec.push(value);
-                                Value::List(vec)
-                            }
-                        };
-                        hm.insert(key, value_to_insert);
-                    }
-                    Some(other_value) => {
-                        hm.insert(key, Value::List(vec![other_value, value]));
-                    }
-                }
-            }
-            Ok((remainder, hm))
-        }
-        Err(e) => Err(e),
-    }
+fn parse_key<'a, E: NomParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Key<'a>, E> {
+    alt((map(parse_quoted, Key::Quoted), map(parse_token, Key::Bare)))(input)
 }
 
-fn parse_map_kv_list(input: &str) -> IResult<&str, Vec<(&str, Value)>> {
-    delimited(
-        multispace0,
-        separated_list1(multispace1, parse_map_key_value_pair),
-        multispace0,
-    )(input)
+fn parse_operator<'a, E: NomParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Operator, E> {
+    alt((
+        value(Operator::Exact, tag("==")),
+        value(Operator::NotEqual, tag("!=")),
+        value(Operator::Exists, tag("?=")),
+        value(Operator::LessThanEqual, tag("<=")),
+        value(Operator::GreaterThanEqual, tag(">=")),
+        value(Operator::Equal, tag("=")),
+        value(Operator::LessThan, tag("<")),
+        value(Operator::GreaterThan, tag(">")),
+    ))(input)
 }
 
-fn parse_map_key_value_pair<'a>(input: &'a str) -> IResult<&str, (&str, Value<'a>)> {
-    separated_pair(
-        alt((parse_str, parse_unquoted_str)),
-        parse_map_key_value_separator,
+fn parse_value<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Value<'a>, E> {
+    preceded(
+        ws,
         alt((
-            // key can be missing in some cases, see test_skipped_key_in_mapping.
-            // in this case, discard the second key
-            preceded(
-                pair(parse_unquoted_str, parse_map_key_value_separator),
-                parse_value,
-            ),
-            parse_value,
+            context("block", parse_block),
+            context("quoted", map(parse_quoted, Value::Quoted)),
+            context("inline math", map(parse_inline_math, Value::Str)),
+            context("color", parse_color),
+            map(parse_token, scalar),
         )),
     )(input)
 }
 
-fn parse_map_key_value_separator(input: &str) -> IResult<&str, &str> {
-    delimited(multispace0, tag("="), multispace0)(input)
+fn parse_block<'a, E: NomParseError<&'a str> + ContextError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Value<'a>, E> {
+    map(
+        preceded(
+            char('{'),
+            cut(terminated(parse_items, preceded(ws, char('}')))),
+        ),
+        block,
+    )(input)
 }
 
-fn parse_date_str(input: &str) -> IResult<&str, &str> {
-    recognize(tuple((
-        take_while(|c: char| c.is_digit(10)),
-        char('.'),
-        take_while(|c: char| c.is_digit(10)),
-        char('.'),
-        take_while(|c: char| c.is_digit(10)),
-    )))(input)
+fn block(items: Vec<Item<'_>>) -> Value<'_> {
+    if items.iter().all(|item| matches!(item, Item::Value(_))) {
+        Value::List(
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Item::Value(value) => Some(value),
+                    Item::Field(_) => None,
+                })
+                .collect(),
+        )
+    } else if items.iter().all(|item| matches!(item, Item::Field(_))) {
+        Value::Map(
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Item::Field(field) => Some(field),
+                    Item::Value(_) => None,
+                })
+                .collect(),
+        )
+    } else {
+        Value::Mixed(items)
+    }
 }
 
-fn parse_int(input: &str) -> IResult<&str, i64> {
-    preceded(
-        multispace0,
-        map_res(recognize(double), |s: &str| s.parse::<i64>()),
+fn parse_color<'a, E: NomParseError<&'a str>>(input: &'a str) -> IResult<&'a str, Value<'a>, E> {
+    map(
+        pair(
+            alt((tag("rgb"), tag("hsv360"), tag("hsv"))),
+            preceded(
+                pair(ws, char('{')),
+                cut(terminated(
+                    many0(preceded(
+                        ws,
+                        verify(map(parse_token, scalar), |channel| {
+                            matches!(channel, Value::Int(_) | Value::Float(_))
+                        }),
+                    )),
+                    pair(ws, char('}')),
+                )),
+            ),
+        ),
+        |(space, channels)| Value::Color { space, channels },
     )(input)
 }
 
-fn parse_float(input: &str) -> IResult<&str, f64> {
-    preceded(
-        multispace0,
-        map_res(recognize(double), |s: &str| s.parse::<f64>()),
-    )(input)
+/// `@[ tier1cost * 2 ]`, also written `@\[ ... ]` in older files.
+fn parse_inline_math<'a, E: NomParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
+    recognize(pair(
+        alt((tag("@["), tag("@\\["))),
+        cut(pair(take_till(|c| c == ']'), char(']'))),
+    ))(input)
 }
 
-fn parse_str(input: &str) -> IResult<&str, &str> {
-    preceded(
-        multispace0,
-        delimited(
-            tag("\""),
-            escaped(none_of("\"\\"), '\\', one_of("\"")),
-            tag("\""),
-        ),
-    )(input)
+pub fn is_token_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '{' | '}' | '=' | '<' | '>' | '!' | '?' | '"' | '#')
 }
 
-fn _is_valid_unquoted_str_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_' || c == ':' || c == '.'
+fn parse_token<'a, E: NomParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    take_while1(is_token_char)(input)
 }
 
-fn parse_unquoted_str(input: &str) -> IResult<&str, &str> {
-    preceded(multispace0, take_while1(_is_valid_unquoted_str_char))(input)
+/// Reads an unquoted token as a number when it is one, dates like
+/// `2200.01.01` stay strings.
+fn scalar(token: &str) -> Value<'_> {
+    let digits = token.strip_prefix(['-', '+']).unwrap_or(token);
+    let mut parts = digits.split('.');
+    let (integer, fraction) = (parts.next(), parts.next());
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if parts.next().is_some() || digits.is_empty() || digits == "." {
+        return Value::Str(token);
+    }
+    match (integer, fraction) {
+        (Some(integer), None) if is_digits(integer) => {
+            token.parse().map(Value::Int).unwrap_or(Value::Str(token))
+        }
+        (Some(integer), Some(fraction)) if is_digits(integer) && is_digits(fraction) => {
+            token.parse().map(Value::Float).unwrap_or(Value::Str(token))
+        }
+        _ => Value::Str(token),
+    }
+}
+
+/// A quoted string, where a backslash escapes the next character.
+fn parse_quoted<'a, E: NomParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, Cow<'a, str>, E> {
+    let (content, _) = char('"')(input)?;
+    let mut escaped = false;
+    for (i, c) in content.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok((&content[i + 1..], unescape(&content[..i]))),
+            _ => {}
+        }
+    }
+    Err(nom::Err::Failure(E::from_char(
+        &content[content.len()..],
+        '"',
+    )))
+}
+
+/// Unescapes `\"` and `\\`, other sequences like `\n` are kept as written.
+fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('"' | '\\'))) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
 }
 
 #[allow(dead_code)]
-pub fn debug_str(input: &str) -> () {
+pub fn debug_str(input: &str) {
     let prefix = &input[..min(input.len(), 150)];
     println!("{:?}", prefix);
     println!("{}", prefix);
@@ -245,72 +697,319 @@ pub fn debug_str(input: &str) -> () {
 mod tests {
     use super::*;
 
+    // Documents parsed with `parse_file`.
+    const VARIABLES: &str = "@tier1cost = 500\ncost = @tier1cost";
+    const INLINE_MATH: &str = r"cost = @[ tier1cost * 2 ] weight = @\[ a + 1 ]";
+    const OPERATORS: &str = "a=1 b==2 c!=3 d<4 e<=5 f>6 g>=7 h?=8 i > 9";
+    const COLORS: &str =
+        "color = rgb { 255 128 0 } flag = hsv{ 0.5 0.2 0.8 } map = hsv360 { 90 20 80 }";
+    const QUOTED_KEYS: &str = r#""key with \"quotes\""=1 "plain"=2"#;
+    const COMMENTS: &str =
+        "# header\nkey = value # trailing\nlist = { # inside\n 1 2 # two\n }\n# end";
+    const LISTS: &str = r#"key1=value1
+                key2={ list of values }
+                key3={ {} {1 2 3} }"#;
+    const SKIPPED_KEY: &str = r#"expired=yes
+        event_id=					scope={
+        type=none
+        id=0
+        random={ 0 3991148998 }
+        }"#;
+    const REQUIRED_DLCS: &str = r#"
+            required_dlcs={
+                "Ancient Relics Story Pack"
+                "Anniversary Portraits"
+                "Apocalypse"
+            }"#;
+    const SHIP_NAMES: &str = r#"
+            ship_names={
+                "HUMAN1_SHIP_Drake"=1
+                "HUMAN1_SHIP_Shenandoah"=1
+                "HUMAN1_SHIP_Chaoyang"=1
+            }"#;
+    const FLAG: &str = r#"
+            flag={
+                icon={
+                    category="human"
+                    file="flag_human_9.dds"
+                }
+                background={
+                    category="backgrounds"
+                    file="00_solid.dds"
+                }
+                colors={
+                    "blue"
+                    "black"
+                    "null"
+                    "null"
+                }
+            }"#;
+    const INTEL: &str = r#"intel={ { 77 { intel=10 stale_intel={ } } } }"#;
+    const ESCAPED_QUOTES: &str = r#"species_bio="Description contains a \"quoted\" word."
+                   name_list="MAM2"
+                   gender=not_set
+                   trait="trait_resilient""#;
+
+    // Blocks parsed with `parse_value`.
+    const MIXED_BLOCK: &str = "{ 1 2 key=value { 3 } }";
+    const DATES: &str = "{2=2243.01.03 9=2243.01.10 12=2243.01.13}";
+    const NESTED: &str =
+        "{x=1 y={x=1 y=73.0 z=\"asdf\"\na={\"Anniversary Portraits\"\n \t\"Apocalypse\"}}}";
+    const INTEL_MANAGER: &str = "{intel_manager={ intel={ { 13 { intel=0 stale_intel={} } } { 62 {intel=0 stale_intel={}}} { 63 {intel=0 stale_intel={}}} }}}";
+    const INTEL_MANAGER_STALE: &str =
+        "{intel_manager={ intel={ { 67 { intel=10 stale_intel={ } } } } }}";
+    const OPERATORS_IN_BLOCK: &str = "{x=1 y>2 x=3 c=rgb{1 2 3} m={1 a=b}}";
+    const REPEATED_KEYS: &str = r#"{x=1 x=1 y=1 y=2 z=1 z="asdf"}"#;
+    const REPEATED_BLOCKS: &str = "{x={1 1 1} x={2 2 2} x={3 3 3}}";
+    const INTERLEAVED_KEYS: &str = "{x=1 y=1 x=2 y=2 z=1 x=3}";
+
+    const DOCUMENTS: [&str; 13] = [
+        VARIABLES,
+        INLINE_MATH,
+        OPERATORS,
+        COLORS,
+        QUOTED_KEYS,
+        COMMENTS,
+        LISTS,
+        SKIPPED_KEY,
+        REQUIRED_DLCS,
+        SHIP_NAMES,
+        FLAG,
+        INTEL,
+        ESCAPED_QUOTES,
+    ];
+    const BLOCKS: [&str; 9] = [
+        MIXED_BLOCK,
+        DATES,
+        NESTED,
+        INTEL_MANAGER,
+        INTEL_MANAGER_STALE,
+        OPERATORS_IN_BLOCK,
+        REPEATED_KEYS,
+        REPEATED_BLOCKS,
+        INTERLEAVED_KEYS,
+    ];
+
+    fn deep_nesting() -> String {
+        let depth = 250;
+        format!(
+            "outer_key={}{}{}",
+            "{".repeat(depth),
+            "key=value",
+            "}".repeat(depth)
+        )
+    }
+
+    fn field<'a>(key: &'a str, value: Value<'a>) -> Field<'a> {
+        Field {
+            key: Key::Bare(key),
+            op: Operator::Equal,
+            value,
+        }
+    }
+
+    fn parse(input: &str) -> IResult<&str, Value<'_>> {
+        parse_value(input)
+    }
+
+    fn quoted(s: &str) -> Value<'_> {
+        Value::Quoted(Cow::Borrowed(s))
+    }
+
     #[test]
     fn test_parse_int() {
-        assert_eq!(parse_value("123"), Ok(("", Value::Int(123))));
-        assert_eq!(parse_value("0"), Ok(("", Value::Int(0))));
-        assert_eq!(parse_value(" 007"), Ok(("", Value::Int(7))));
-        assert_eq!(parse_value("-1"), Ok(("", Value::Int(-1))));
+        assert_eq!(parse("123"), Ok(("", Value::Int(123))));
+        assert_eq!(parse("0"), Ok(("", Value::Int(0))));
+        assert_eq!(parse(" 007"), Ok(("", Value::Int(7))));
+        assert_eq!(parse("-1"), Ok(("", Value::Int(-1))));
     }
 
     #[test]
     fn test_parse_float() {
-        assert_eq!(parse_value("1.0"), Ok(("", Value::Float(1.0))));
-        assert_eq!(parse_value("-1.0"), Ok(("", Value::Float(-1.0))));
+        assert_eq!(parse("1.0"), Ok(("", Value::Float(1.0))));
+        assert_eq!(parse("-1.0"), Ok(("", Value::Float(-1.0))));
+        assert_eq!(parse(".5"), Ok(("", Value::Float(0.5))));
     }
 
     #[test]
     fn test_parse_date() {
+        assert_eq!(parse("2200.04.03"), Ok(("", Value::Str("2200.04.03"))));
+        assert_eq!(parse("2243.01.03"), Ok(("", Value::Str("2243.01.03"))));
+        assert_eq!(parse("1.1.1"), Ok(("", Value::Str("1.1.1"))));
+    }
+
+    #[test]
+    fn test_parse_str() {
+        assert_eq!(parse("\"word\""), Ok(("", quoted("word"))));
         assert_eq!(
-            parse_value("2200.04.03"),
-            Ok(("", Value::Str("2200.04.03")))
+            parse("\"This is a full sentence!.?\""),
+            Ok(("", quoted("This is a full sentence!.?")))
         );
         assert_eq!(
-            parse_value("2243.01.03"),
-            Ok(("", Value::Str("2243.01.03")))
+            parse("\"Unicode ßäöü😂\""),
+            Ok(("", quoted("Unicode ßäöü😂")))
+        );
+        assert_eq!(
+            parse("\"flag_human_9.dds\""),
+            Ok(("", quoted("flag_human_9.dds")))
+        );
+        assert_eq!(parse(r#""\"Escaped\"""#), Ok(("", quoted(r#""Escaped""#))));
+        assert_eq!(
+            parse(r#""back\\slash \n kept""#),
+            Ok(("", quoted(r#"back\slash \n kept"#)))
+        );
+        assert_eq!(parse(r#""""#), Ok(("", quoted(""))));
+        assert_eq!(
+            parse("\"# not a comment\""),
+            Ok(("", quoted("# not a comment")))
         );
-        assert_eq!(parse_value("1.1.1"), Ok(("", Value::Str("1.1.1"))));
     }
 
     #[test]
-    fn test_parse_str() {
-        assert_eq!(parse_value("\"word\""), Ok(("", Value::Str("word"))));
+    fn test_parse_unquoted_str() {
+        assert_eq!(parse("traits"), Ok(("", Value::Str("traits"))));
+        assert_eq!(
+            parse("target:debris_field_01"),
+            Ok(("", Value::Str("target:debris_field_01")))
+        );
+        assert_eq!(parse("none"), Ok(("", Value::Str("none"))));
+        assert_eq!(parse("infantry"), Ok(("", Value::Str("infantry"))));
+        assert_eq!(parse("1e5"), Ok(("", Value::Str("1e5"))));
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        assert_eq!(
+            parse_file(VARIABLES),
+            Ok(Value::Map(vec![
+                field("@tier1cost", Value::Int(500)),
+                field("cost", Value::Str("@tier1cost")),
+            ]))
+        );
+        assert_eq!(
+            parse_file(INLINE_MATH),
+            Ok(Value::Map(vec![
+                field("cost", Value::Str("@[ tier1cost * 2 ]")),
+                field("weight", Value::Str(r"@\[ a + 1 ]")),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let compare = |key, op, value| Field {
+            key: Key::Bare(key),
+            op,
+            value,
+        };
         assert_eq!(
-            parse_value("\"This is a full sentence!.?\""),
-            Ok(("", Value::Str("This is a full sentence!.?")))
+            parse_file(OPERATORS),
+            Ok(Value::Map(vec![
+                compare("a", Operator::Equal, Value::Int(1)),
+                compare("b", Operator::Exact, Value::Int(2)),
+                compare("c", Operator::NotEqual, Value::Int(3)),
+                compare("d", Operator::LessThan, Value::Int(4)),
+                compare("e", Operator::LessThanEqual, Value::Int(5)),
+                compare("f", Operator::GreaterThan, Value::Int(6)),
+                compare("g", Operator::GreaterThanEqual, Value::Int(7)),
+                compare("h", Operator::Exists, Value::Int(8)),
+                compare("i", Operator::GreaterThan, Value::Int(9)),
+            ]))
         );
+    }
+
+    #[test]
+    fn test_parse_color() {
         assert_eq!(
-            parse_value("\"Unicode ßäöü😂\""),
-            Ok(("", Value::Str("Unicode ßäöü😂")))
+            parse_file(COLORS),
+            Ok(Value::Map(vec![
+                field(
+                    "color",
+                    Value::Color {
+                        space: "rgb",
+                        channels: vec![Value::Int(255), Value::Int(128), Value::Int(0)],
+                    }
+                ),
+                field(
+                    "flag",
+                    Value::Color {
+                        space: "hsv",
+                        channels: vec![Value::Float(0.5), Value::Float(0.2), Value::Float(0.8)],
+                    }
+                ),
+                field(
+                    "map",
+                    Value::Color {
+                        space: "hsv360",
+                        channels: vec![Value::Int(90), Value::Int(20), Value::Int(80)],
+                    }
+                ),
+            ]))
         );
+        // Not followed by a block, these are plain identifiers.
         assert_eq!(
-            parse_value("\"flag_human_9.dds\""),
-            Ok(("", Value::Str("flag_human_9.dds")))
+            parse_file("rgb = hsv"),
+            Ok(Value::Map(vec![field("rgb", Value::Str("hsv"))]))
         );
+    }
+
+    #[test]
+    fn test_parse_quoted_keys() {
         assert_eq!(
-            parse_value(r#""\"Escaped\"""#),
-            Ok(("", Value::Str(r#"\"Escaped\""#)))
+            parse_file(QUOTED_KEYS),
+            Ok(Value::Map(vec![
+                Field {
+                    key: Key::Quoted(Cow::Borrowed(r#"key with "quotes""#)),
+                    op: Operator::Equal,
+                    value: Value::Int(1),
+                },
+                Field {
+                    key: Key::Quoted(Cow::Borrowed("plain")),
+                    op: Operator::Equal,
+                    value: Value::Int(2),
+                },
+            ]))
         );
     }
 
     #[test]
-    fn test_parse_unquoted_str() {
-        assert_eq!(parse_value("traits"), Ok(("", Value::Str("traits"))));
+    fn test_parse_comments() {
         assert_eq!(
-            parse_value("target:debris_field_01"),
-            Ok(("", Value::Str("target:debris_field_01")))
+            parse_file(COMMENTS),
+            Ok(Value::Map(vec![
+                field("key", Value::Str("value")),
+                field("list", Value::List(vec![Value::Int(1), Value::Int(2)])),
+            ]))
+        );
+        assert_eq!(parse_file("# only a comment"), Ok(Value::Map(Vec::new())));
+        assert_eq!(parse_file(""), Ok(Value::Map(Vec::new())));
+    }
+
+    #[test]
+    fn test_parse_mixed_block() {
+        assert_eq!(
+            parse(MIXED_BLOCK),
+            Ok((
+                "",
+                Value::Mixed(vec![
+                    Item::Value(Value::Int(1)),
+                    Item::Value(Value::Int(2)),
+                    Item::Field(field("key", Value::Str("value"))),
+                    Item::Value(Value::List(vec![Value::Int(3)])),
+                ])
+            ))
         );
     }
 
     #[test]
     fn test_parse_list() {
-        assert_eq!(parse_value("{}"), Ok(("", Value::List(Vec::new()))));
+        assert_eq!(parse("{}"), Ok(("", Value::List(Vec::new()))));
         assert_eq!(
-            parse_value("{1}"),
+            parse("{1}"),
             Ok(("", Value::List(Vec::from([Value::Int(1)]))))
         );
         assert_eq!(
-            parse_value("{1 2\t3\n4}"),
+            parse("{1 2\t3\n4}"),
             Ok((
                 "",
                 Value::List(Vec::from([
@@ -322,23 +1021,19 @@ mod tests {
             ))
         );
         assert_eq!(
-            parse_value("{1 \"text\" 3}"),
+            parse("{1 \"text\" 3}"),
             Ok((
                 "",
-                Value::List(Vec::from([
-                    Value::Int(1),
-                    Value::Str("text"),
-                    Value::Int(3)
-                ]))
+                Value::List(Vec::from([Value::Int(1), quoted("text"), Value::Int(3)]))
             ))
         );
         assert_eq!(
-            parse_value("{1 {\"inner\"} 3}"),
+            parse("{1 {\"inner\"} 3}"),
             Ok((
                 "",
                 Value::List(Vec::from([
                     Value::Int(1),
-                    Value::List(Vec::from([Value::Str("inner")])),
+                    Value::List(Vec::from([quoted("inner")])),
                     Value::Int(3)
                 ]))
             ))
@@ -348,18 +1043,14 @@ mod tests {
     #[test]
     fn test_parse_map() {
         assert_eq!(
-            parse_value("{a.1=2}"),
-            Ok(("", Value::Map(HashMap::from([(("a.1"), Value::Int(2)),]))))
+            parse("{a.1=2}"),
+            Ok(("", Value::Map(vec![field("a.1", Value::Int(2))])))
         );
         assert_eq!(
-            parse_file(
-                r#"key1=value1
-                key2={ list of values }
-                key3={ {} {1 2 3} }"#
-            ),
-            Ok(Value::Map(HashMap::from([
-                ("key1", Value::Str("value1")),
-                (
+            parse_file(LISTS),
+            Ok(Value::Map(vec![
+                field("key1", Value::Str("value1")),
+                field(
                     "key2",
                     Value::List(vec![
                         Value::Str("list"),
@@ -367,341 +1058,382 @@ mod tests {
                         Value::Str("values")
                     ])
                 ),
-                (
+                field(
                     "key3",
                     Value::List(vec![
                         Value::List(vec![]),
                         Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
                     ])
                 ),
-            ])))
+            ]))
         );
 
         assert_eq!(
-            parse_value("{2=2243.01.03 9=2243.01.10 12=2243.01.13}"),
+            parse(DATES),
             Ok((
                 "",
-                Value::Map(HashMap::from([
-                    (("2"), Value::Str("2243.01.03")),
-                    (("9"), Value::Str("2243.01.10")),
-                    (("12"), Value::Str("2243.01.13")),
-                ]))
+                Value::Map(vec![
+                    field("2", Value::Str("2243.01.03")),
+                    field("9", Value::Str("2243.01.10")),
+                    field("12", Value::Str("2243.01.13")),
+                ])
             ))
         );
 
         assert_eq!(
-            parse_value("{x=1\ny=73.0}"),
+            parse("{x=1\ny=73.0}"),
             Ok((
                 "",
-                Value::Map(HashMap::from([
-                    (("x"), Value::Int(1)),
-                    (("y"), Value::Float(73.0)),
-                ]))
+                Value::Map(vec![
+                    field("x", Value::Int(1)),
+                    field("y", Value::Float(73.0))
+                ])
             ))
         );
         assert_eq!(
-            parse_value(
-                "{x=1 y={x=1 y=73.0 z=\"asdf\"\na={\"Anniversary Portraits\"\n \t\"Apocalypse\"}}}"
-            ),
+            parse(NESTED),
             Ok((
                 "",
-                Value::Map(HashMap::from([
-                    (("x"), Value::Int(1)),
-                    (
-                        ("y"),
-                        Value::Map(HashMap::from([
-                            (("x"), Value::Int(1)),
-                            (("y"), Value::Float(73.0)),
-                            (("z"), Value::Str("asdf")),
-                            (
-                                ("a"),
-                                Value::List(Vec::from([
-                                    Value::Str("Anniversary Portraits"),
-                                    Value::Str("Apocalypse")
-                                ]))
+                Value::Map(vec![
+                    field("x", Value::Int(1)),
+                    field(
+                        "y",
+                        Value::Map(vec![
+                            field("x", Value::Int(1)),
+                            field("y", Value::Float(73.0)),
+                            field("z", quoted("asdf")),
+                            field(
+                                "a",
+                                Value::List(vec![
+                                    quoted("Anniversary Portraits"),
+                                    quoted("Apocalypse")
+                                ])
                             ),
-                        ]))
+                        ])
                     ),
-                ]))
+                ])
             ))
         );
 
+        let intel = |id| {
+            Value::List(vec![
+                Value::Int(id),
+                Value::Map(vec![
+                    field("intel", Value::Int(0)),
+                    field("stale_intel", Value::List(vec![])),
+                ]),
+            ])
+        };
         assert_eq!(
-            parse_value("{intel_manager={ intel={ { 13 { intel=0 stale_intel={} } } { 62 {intel=0 stale_intel={}}} { 63 {intel=0 stale_intel={}}} }}}"),
+            parse(INTEL_MANAGER),
             Ok((
                 "",
-                Value::Map(HashMap::from([
-                    (
-                        "intel_manager",
-                        Value::Map(HashMap::from([
-                            (
-                                "intel",
-                                Value::List(
-                                    vec![
-                                        Value::List(vec![
-                                            Value::Int(13), Value::Map(HashMap::from([("intel", Value::Int(0)), ("stale_intel", Value::List(vec![]))])),
-                                        ]),
-                                        Value::List(vec![
-                                            Value::Int(62), Value::Map(HashMap::from([("intel", Value::Int(0)), ("stale_intel", Value::List(vec![]))])),
-                                        ]),
-                                        Value::List(vec![
-                                            Value::Int(63), Value::Map(HashMap::from([("intel", Value::Int(0)), ("stale_intel", Value::List(vec![]))])),
-                                        ]),
-                                    ]
-                                )
-                            )
-                        ]))
-                    )
-                ]))
-            )
-            ));
+                Value::Map(vec![field(
+                    "intel_manager",
+                    Value::Map(vec![field(
+                        "intel",
+                        Value::List(vec![intel(13), intel(62), intel(63)])
+                    )])
+                )])
+            ))
+        );
         assert_eq!(
-            parse_value("{intel_manager={ intel={ { 67 { intel=10 stale_intel={ } } } } }}"),
+            parse(INTEL_MANAGER_STALE),
             Ok((
                 "",
-                Value::Map(HashMap::from([(
+                Value::Map(vec![field(
                     "intel_manager",
-                    Value::Map(HashMap::from([(
+                    Value::Map(vec![field(
                         "intel",
                         Value::List(vec![Value::List(vec![
                             Value::Int(67),
-                            Value::Map(HashMap::from([
-                                ("intel", Value::Int(10)),
-                                ("stale_intel", Value::List(vec![]))
-                            ])),
-                        ]),])
-                    )]))
-                )]))
+                            Value::Map(vec![
+                                field("intel", Value::Int(10)),
+                                field("stale_intel", Value::List(vec![]))
+                            ]),
+                        ])])
+                    )])
+                )])
             ))
         );
     }
 
     #[test]
     fn test_json_representation() {
-        let v = parse_value("{intel_manager={intel={{13 {intel=0 stale_intel={}}} {62 {intel=0 stale_intel={}}} {63 {intel=0 stale_intel={}}} }}}").expect("").1;
+        let v = parse(INTEL_MANAGER).expect("").1;
         assert_eq!(
             serde_json::json!(v).to_string(),
             r#"{"intel_manager":{"intel":[[13,{"intel":0,"stale_intel":[]}],[62,{"intel":0,"stale_intel":[]}],[63,{"intel":0,"stale_intel":[]}]]}}"#
         );
 
-        let v = parse_value("{intel_manager={ intel={ { 67 { intel=10 stale_intel={ } } } } }}")
-            .expect("")
-            .1;
+        let v = parse(INTEL_MANAGER_STALE).expect("").1;
         assert_eq!(
             serde_json::json!(v).to_string(),
             r#"{"intel_manager":{"intel":[[67,{"intel":10,"stale_intel":[]}]]}}"#
         );
+
+        let v = parse(OPERATORS_IN_BLOCK).expect("").1;
+        assert_eq!(
+            serde_json::json!(v).to_string(),
+            r#"{"c":{"rgb":[1,2,3]},"m":[1,{"a":"b"}],"x":[1,3],"y":{">":2}}"#
+        );
     }
 
     #[test]
     fn test_parse_map_repeated_key() {
         assert_eq!(
-            parse_value(r#"{x=1 x=1 y=1 y=2 z=1 z="asdf"}"#),
+            parse(REPEATED_KEYS),
             Ok((
                 "",
-                Value::Map(HashMap::from([
-                    ("x", Value::List(Vec::from([Value::Int(1), Value::Int(1)]))),
-                    ("y", Value::List(Vec::from([Value::Int(1), Value::Int(2)]))),
-                    (
-                        "z",
-                        Value::List(Vec::from([Value::Int(1), Value::Str("asdf")]))
-                    ),
-                ]))
+                Value::Map(vec![
+                    field("x", Value::Int(1)),
+                    field("x", Value::Int(1)),
+                    field("y", Value::Int(1)),
+                    field("y", Value::Int(2)),
+                    field("z", Value::Int(1)),
+                    field("z", quoted("asdf")),
+                ])
             ))
         );
+        // Repeated blocks stay apart from a single block holding lists.
+        let repeated = parse(REPEATED_BLOCKS).unwrap().1;
+        let ones = Value::List(vec![Value::Int(1); 3]);
+        assert_eq!(repeated.fields().count(), 3);
+        assert_eq!(repeated.get("x"), Some(&ones));
+        assert_ne!(repeated, parse("{x={{1 1 1} {2 2 2} {3 3 3}}}").unwrap().1);
+    }
+
+    #[test]
+    fn test_parse_map_interleaved_keys() {
+        let interleaved = parse(INTERLEAVED_KEYS).unwrap().1;
         assert_eq!(
-            parse_value("{x={1 1 1} x={2 2 2} x={3 3 3}}").unwrap().1,
-            Value::Map(HashMap::from([(
-                "x",
-                Value::List(Vec::from([
-                    Value::List(Vec::from([Value::Int(1), Value::Int(1), Value::Int(1)])),
-                    // when the second key with x is encountered, values above should be place in a NESTED list
-                    Value::List(Vec::from([Value::Int(2), Value::Int(2), Value::Int(2)])),
-                    Value::List(Vec::from([Value::Int(3), Value::Int(3), Value::Int(3)])),
-                ]))
-            ),]))
+            interleaved,
+            Value::Map(vec![
+                field("x", Value::Int(1)),
+                field("y", Value::Int(1)),
+                field("x", Value::Int(2)),
+                field("y", Value::Int(2)),
+                field("z", Value::Int(1)),
+                field("x", Value::Int(3)),
+            ])
+        );
+        assert_eq!(interleaved.get("x"), Some(&Value::Int(1)));
+        // The JSON groups every value of a key where the key first appears.
+        assert_eq!(
+            serde_json::to_string(&interleaved).unwrap(),
+            r#"{"x":[1,2,3],"y":[1,2],"z":1}"#
         );
     }
 
     #[test]
     fn test_deep_nested_object() {
-        let test_depth = 250;
-        let test_input = format!(
-            "outer_key={}{}{}",
-            "{".repeat(test_depth),
-            "key=value",
-            "}".repeat(test_depth)
-        );
-        // println!("{}", test_input);
-        parse_file(test_input.as_str()).expect("Should parse");
+        parse_file(&deep_nesting()).expect("Should parse");
     }
 
     #[test]
     fn test_skipped_key_in_mapping() {
         assert_eq!(
-            parse_map_key_value_pair("key=other_key=value_1").expect("asdf"),
-            ("", ("key", Value::Str("value_1")))
+            parse_field::<Error<&str>>("key=other_key=value_1").expect("asdf"),
+            ("", field("key", Value::Str("value_1")))
         );
 
         assert_eq!(
-            parse_map_kv_list("key=other_key=value_2").expect("asdf"),
-            ("", vec![("key", Value::Str("value_2"))])
+            parse_items::<Error<&str>>("key=other_key=value_2").expect("asdf"),
+            ("", vec![Item::Field(field("key", Value::Str("value_2")))])
         );
 
         // basic example:
         assert_eq!(
             parse_file("key=other_key=value"),
-            Ok(Value::Map(HashMap::from([("key", Value::Str("value")),])))
+            Ok(Value::Map(vec![field("key", Value::Str("value"))]))
         );
         // example found in real save:
-        let save_content = r#"expired=yes
-        event_id=					scope={
-        type=none
-        id=0
-        random={ 0 3991148998 }
-        }"#;
         assert_eq!(
-            parse_file(save_content).unwrap(),
-            Value::Map(HashMap::from([
-                ("expired", Value::Str("yes")),
-                (
+            parse_file(SKIPPED_KEY).unwrap(),
+            Value::Map(vec![
+                field("expired", Value::Str("yes")),
+                field(
                     "event_id",
-                    Value::Map(HashMap::from([
-                        ("type", Value::Str("none")),
-                        ("id", Value::Int(0)),
-                        (
+                    Value::Map(vec![
+                        field("type", Value::Str("none")),
+                        field("id", Value::Int(0)),
+                        field(
                             "random",
                             Value::List(vec![Value::Int(0), Value::Int(3991148998)])
                         ),
-                    ]))
+                    ])
                 ),
-            ]))
+            ])
         )
     }
 
     #[test]
     fn test_parse_file() {
         assert_eq!(
-            parse_file(
-                r#"
-            required_dlcs={
-                "Ancient Relics Story Pack"
-                "Anniversary Portraits"
-                "Apocalypse"
-            }"#
-            )
-            .unwrap(),
-            Value::Map(HashMap::from([(
+            parse_file(REQUIRED_DLCS).unwrap(),
+            Value::Map(vec![field(
                 "required_dlcs",
-                Value::List(Vec::from([
-                    Value::Str("Ancient Relics Story Pack"),
-                    Value::Str("Anniversary Portraits"),
-                    Value::Str("Apocalypse"),
-                ]))
-            ),]))
+                Value::List(vec![
+                    quoted("Ancient Relics Story Pack"),
+                    quoted("Anniversary Portraits"),
+                    quoted("Apocalypse"),
+                ])
+            )])
         );
+        let ship_name = |name| Field {
+            key: Key::Quoted(Cow::Borrowed(name)),
+            op: Operator::Equal,
+            value: Value::Int(1),
+        };
         assert_eq!(
-            parse_file(
-                r#"
-            ship_names={
-                "HUMAN1_SHIP_Drake"=1
-                "HUMAN1_SHIP_Shenandoah"=1
-                "HUMAN1_SHIP_Chaoyang"=1
-            }"#
-            )
-            .unwrap(),
-            Value::Map(HashMap::from([(
+            parse_file(SHIP_NAMES).unwrap(),
+            Value::Map(vec![field(
                 "ship_names",
-                Value::Map(HashMap::from([
-                    (("HUMAN1_SHIP_Drake"), Value::Int(1)),
-                    (("HUMAN1_SHIP_Shenandoah"), Value::Int(1)),
-                    (("HUMAN1_SHIP_Chaoyang"), Value::Int(1)),
-                ]))
-            ),]))
+                Value::Map(vec![
+                    ship_name("HUMAN1_SHIP_Drake"),
+                    ship_name("HUMAN1_SHIP_Shenandoah"),
+                    ship_name("HUMAN1_SHIP_Chaoyang"),
+                ])
+            )])
         );
         assert_eq!(
-            parse_file(
-                r#"
-            flag={
-                icon={
-                    category="human"
-                    file="flag_human_9.dds"
-                }
-                background={
-                    category="backgrounds"
-                    file="00_solid.dds"
-                }
-                colors={
-                    "blue"
-                    "black"
-                    "null"
-                    "null"
-                }
-            }"#
-            )
-            .unwrap(),
-            Value::Map(HashMap::from([(
-                ("flag"),
-                Value::Map(HashMap::from([
-                    (
-                        ("icon"),
-                        Value::Map(HashMap::from([
-                            (("category"), Value::Str("human")),
-                            (("file"), Value::Str("flag_human_9.dds")),
-                        ]))
+            parse_file(FLAG).unwrap(),
+            Value::Map(vec![field(
+                "flag",
+                Value::Map(vec![
+                    field(
+                        "icon",
+                        Value::Map(vec![
+                            field("category", quoted("human")),
+                            field("file", quoted("flag_human_9.dds")),
+                        ])
                     ),
-                    (
-                        ("background"),
-                        Value::Map(HashMap::from([
-                            (("category"), Value::Str("backgrounds")),
-                            (("file"), Value::Str("00_solid.dds")),
-                        ]))
+                    field(
+                        "background",
+                        Value::Map(vec![
+                            field("category", quoted("backgrounds")),
+                            field("file", quoted("00_solid.dds")),
+                        ])
                     ),
-                    (
-                        ("colors"),
-                        Value::List(Vec::from([
-                            Value::Str("blue"),
-                            Value::Str("black"),
-                            Value::Str("null"),
-                            Value::Str("null"),
-                        ]))
+                    field(
+                        "colors",
+                        Value::List(vec![
+                            quoted("blue"),
+                            quoted("black"),
+                            quoted("null"),
+                            quoted("null"),
+                        ])
                     ),
-                ])),
-            ),]))
+                ])
+            )])
         );
 
         assert_eq!(
-            parse_file(r#"intel={ { 77 { intel=10 stale_intel={ } } } }"#).unwrap(),
-            Value::Map(HashMap::from([(
+            parse_file(INTEL).unwrap(),
+            Value::Map(vec![field(
                 "intel",
-                Value::List(Vec::from([Value::List(Vec::from([
+                Value::List(vec![Value::List(vec![
                     Value::Int(77),
-                    Value::Map(HashMap::from([
-                        ("intel", Value::Int(10)),
-                        ("stale_intel", Value::List(Vec::new())),
-                    ])),
-                ])),]))
-            ),]))
+                    Value::Map(vec![
+                        field("intel", Value::Int(10)),
+                        field("stale_intel", Value::List(Vec::new())),
+                    ]),
+                ])])
+            )])
         );
 
         // Bug report: parser does not handle escaped quotes
         assert_eq!(
-            parse_file(
-                r#"species_bio="Description contains a \"quoted\" word."
-                   name_list="MAM2"
-                   gender=not_set
-                   trait="trait_resilient""#
-            )
-            .unwrap(),
-            Value::Map(HashMap::from([
-                (
+            parse_file(ESCAPED_QUOTES).unwrap(),
+            Value::Map(vec![
+                field(
                     "species_bio",
-                    Value::Str(r#"Description contains a \"quoted\" word."#)
+                    quoted(r#"Description contains a "quoted" word."#)
                 ),
-                ("name_list", Value::Str("MAM2")),
-                ("gender", Value::Str("not_set")),
-                ("trait", Value::Str("trait_resilient")),
-            ]))
-        )
+                field("name_list", quoted("MAM2")),
+                field("gender", Value::Str("not_set")),
+                field("trait", quoted("trait_resilient")),
+            ])
+        );
+
+        // A byte order mark, as written by the game files.
+        assert_eq!(
+            parse_file("\u{feff}a=b"),
+            Ok(Value::Map(vec![field("a", Value::Str("b"))]))
+        );
+        assert!(parse_file("a={ b=c").is_err());
+        assert!(parse_file("a=b }").is_err());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let blocks = BLOCKS.map(|block| format!("block={}", block));
+        let deep = deep_nesting();
+        // Cases of the writer the fixtures above do not cover.
+        let writer_cases = [
+            r#""a\\"=3 bio="Ends with a newline.\n" empty="""#,
+            "rgb = hsv z=-0.5",
+            "",
+        ];
+        let documents = DOCUMENTS
+            .into_iter()
+            .chain(blocks.iter().map(String::as_str))
+            .chain([deep.as_str()])
+            .chain(writer_cases);
+        for document in documents {
+            let value = parse_file(document).unwrap();
+            let written = crate::writer::write_document(&value);
+            assert_eq!(parse_file(&written), Ok(value), "{}", written);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let error = parse_file("a=1\nb={\n\tc=2\n\td={ e=3\n}\n").unwrap_err();
+        assert_eq!(error.message, "expected '}', found end of input");
+        assert_eq!((error.line, error.column, error.offset), (6, 1, 24));
+        let labels: Vec<_> = error
+            .context
+            .iter()
+            .map(|c| (c.label, c.line, c.column))
+            .collect();
+        assert_eq!(labels, vec![("block", 2, 3), ("field", 2, 1)]);
+
+        let error = parse_file("a=1\nb=2 }\nc=3").unwrap_err();
+        assert_eq!(error.message, "unexpected '}'");
+        assert_eq!((error.line, error.column), (2, 5));
+        assert_eq!(error.snippet, "b=2 }");
+        assert_eq!(error.snippet_column, 4);
+        assert!(error.context.is_empty());
+
+        let error = parse_file("name=\"unterminated\nnext=1").unwrap_err();
+        assert_eq!(error.message, "expected '\"', found end of input");
+        assert_eq!(error.context[0].label, "quoted");
+        assert_eq!((error.context[0].line, error.context[0].column), (1, 6));
+
+        let error = parse_file("color = rgb { 255 x 0 }").unwrap_err();
+        assert_eq!(error.message, "expected '}', found 'x'");
+        assert_eq!(error.column, 19);
+
+        let error = parse_file("a = ").unwrap_err();
+        assert_eq!(error.message, "unexpected end of input");
+        assert_eq!(error.context[0].label, "field");
+    }
+
+    #[test]
+    fn test_parse_error_snippet() {
+        let line = format!("{}a=}}{}", "x=1 ".repeat(30), " y=2".repeat(30));
+        let error = parse_file(&line).unwrap_err();
+        assert_eq!(error.offset, 122);
+        assert_eq!(error.snippet.chars().count(), SNIPPET_RADIUS * 2);
+        assert_eq!(&error.snippet[error.snippet_column..][..4], "} y=");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "unexpected '}}' at line 1, column 123\n    {}\n    {}^\n  in field at line 1, column 121",
+                error.snippet,
+                " ".repeat(SNIPPET_RADIUS)
+            )
+        );
     }
 }
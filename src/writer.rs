@@ -0,0 +1,641 @@
+use std::{
+    borrow::Cow,
+    fmt::{Display, Formatter},
+};
+
+use derive_more::Display;
+use serde::{
+    ser::{self, Impossible, SerializeMap, SerializeSeq, SerializeStruct},
+    Serialize, Serializer,
+};
+
+use crate::parser::{is_token_char, Field, Item, Key, Operator, Value};
+
+/// Name of the newtype that marks a list written as a repeated key, see [`repeated`].
+const REPEATED: &str = "$clausewitz::repeated";
+
+/// Serializes a list as one `key=value` per element, like the
+/// `#[jomini(duplicated)]` fields are read. Other formats get a plain list.
+pub fn repeated<T: Serialize + ?Sized, S: Serializer>(
+    values: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(REPEATED, values)
+}
+
+#[derive(Debug, Display)]
+#[display(fmt = "Could not write Clausewitz text: {}", _0)]
+pub struct WriteError(String);
+
+impl std::error::Error for WriteError {}
+
+impl ser::Error for WriteError {
+    fn custom<T: Display>(msg: T) -> Self {
+        WriteError(msg.to_string())
+    }
+}
+
+/// Writes a parsed file back to text, its top level fields without braces.
+#[allow(dead_code)]
+pub fn write_document(value: &Value) -> String {
+    document(node(value))
+}
+
+/// Writes a typed model, like the `Gamestate`, as Clausewitz text.
+///
+/// `None` fields are left out and strings are always quoted.
+#[allow(dead_code)]
+pub fn to_text<T: Serialize + ?Sized>(model: &T) -> Result<String, WriteError> {
+    let node = model.serialize(NodeSerializer)?;
+    Ok(document(node.unwrap_or(Node::Block(Vec::new())).nested()))
+}
+
+/// A value as text, blocks spanning several lines when they hold fields.
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        write_node(&mut out, &node(self), 0);
+        f.write_str(&out)
+    }
+}
+
+/// The text of a value, before it is laid out.
+enum Node<'a> {
+    Scalar(Cow<'a, str>),
+    Block(Vec<Entry<'a>>),
+    /// Values of a repeated key, `technology="a" technology="b"`.
+    Repeated(Vec<Node<'a>>),
+}
+
+struct Entry<'a> {
+    key: Option<(Cow<'a, str>, Operator)>,
+    node: Node<'a>,
+}
+
+impl<'a> Node<'a> {
+    /// Repeated values outside of a field can only be written as a list.
+    fn nested(self) -> Node<'a> {
+        match self {
+            Node::Repeated(nodes) => Node::Block(
+                nodes
+                    .into_iter()
+                    .map(|node| Entry { key: None, node })
+                    .collect(),
+            ),
+            node => node,
+        }
+    }
+}
+
+fn node<'v>(value: &'v Value<'_>) -> Node<'v> {
+    match value {
+        Value::Str(s) => Node::Scalar(Cow::Borrowed(s)),
+        Value::Quoted(s) => Node::Scalar(Cow::Owned(quote(s))),
+        Value::Int(n) => Node::Scalar(Cow::Owned(n.to_string())),
+        Value::Float(x) => Node::Scalar(Cow::Owned(float(*x))),
+        Value::Color { space, channels } => {
+            let channels: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+            Node::Scalar(Cow::Owned(format!(
+                "{} {{ {} }}",
+                space,
+                channels.join(" ")
+            )))
+        }
+        Value::List(values) => Node::Block(
+            values
+                .iter()
+                .map(|value| Entry {
+                    key: None,
+                    node: node(value),
+                })
+                .collect(),
+        ),
+        Value::Map(fields) => Node::Block(fields.iter().map(field).collect()),
+        Value::Mixed(items) => Node::Block(
+            items
+                .iter()
+                .map(|item| match item {
+                    Item::Value(value) => Entry {
+                        key: None,
+                        node: node(value),
+                    },
+                    Item::Field(f) => field(f),
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn field<'v>(field: &'v Field<'_>) -> Entry<'v> {
+    let key = match &field.key {
+        Key::Bare(key) => Cow::Borrowed(*key),
+        Key::Quoted(key) => Cow::Owned(quote(key)),
+    };
+    Entry {
+        key: Some((key, field.op)),
+        node: node(&field.value),
+    }
+}
+
+/// Quotes a string, escaping what the parser unescapes: quotes, and the
+/// backslashes that would otherwise escape a quote or another backslash.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('"', _) => quoted.push_str("\\\""),
+            ('\\', None | Some('"' | '\\')) => quoted.push_str("\\\\"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Keys are left bare when the parser reads them back as a single token.
+fn key_text(key: &str) -> Cow<'_, str> {
+    if !key.is_empty() && key.chars().all(is_token_char) {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(quote(key))
+    }
+}
+
+/// Whole floats keep a decimal point so they are not read back as integers.
+fn float(x: f64) -> String {
+    let text = x.to_string();
+    if x.is_finite() && !text.contains('.') {
+        text + ".0"
+    } else {
+        text
+    }
+}
+
+fn document(node: Node) -> String {
+    let mut out = String::new();
+    match node {
+        Node::Block(entries) => write_entries(&mut out, &entries, 0),
+        node => {
+            write_node(&mut out, &node, 0);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.extend(std::iter::repeat_n('\t', depth));
+}
+
+fn write_entries(out: &mut String, entries: &[Entry], depth: usize) {
+    for entry in entries {
+        match (&entry.key, &entry.node) {
+            (Some(_), Node::Repeated(nodes)) => {
+                for node in nodes {
+                    write_entry(out, &entry.key, node, depth);
+                }
+            }
+            (key, node) => write_entry(out, key, node, depth),
+        }
+    }
+}
+
+fn write_entry(out: &mut String, key: &Option<(Cow<str>, Operator)>, node: &Node, depth: usize) {
+    indent(out, depth);
+    if let Some((key, op)) = key {
+        out.push_str(key);
+        out.push_str(op.symbol());
+    }
+    write_node(out, node, depth);
+    out.push('\n');
+}
+
+fn write_node(out: &mut String, node: &Node, depth: usize) {
+    let entries = match node {
+        Node::Scalar(s) => return out.push_str(s),
+        Node::Block(entries) => entries,
+        Node::Repeated(nodes) => {
+            out.push_str("{ ");
+            for node in nodes {
+                write_node(out, node, depth);
+                out.push(' ');
+            }
+            return out.push('}');
+        }
+    };
+
+    let inline = entries
+        .iter()
+        .all(|entry| entry.key.is_none() && matches!(entry.node, Node::Scalar(_)));
+    if inline {
+        // `{ 1 2 3 }`, and `{ }` when empty.
+        out.push_str("{ ");
+        for entry in entries {
+            write_node(out, &entry.node, depth);
+            out.push(' ');
+        }
+        out.push('}');
+    } else {
+        out.push_str("{\n");
+        write_entries(out, entries, depth + 1);
+        indent(out, depth);
+        out.push('}');
+    }
+}
+
+/// Turns a model into nodes, `None` standing for a value that is left out.
+struct NodeSerializer;
+
+fn scalar(text: String) -> Result<Option<Node<'static>>, WriteError> {
+    Ok(Some(Node::Scalar(Cow::Owned(text))))
+}
+
+fn unsupported<T>(what: &str) -> Result<T, WriteError> {
+    Err(WriteError(format!(
+        "{} have no Clausewitz representation",
+        what
+    )))
+}
+
+impl Serializer for NodeSerializer {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+    type SerializeSeq = SeqNodes;
+    type SerializeTuple = SeqNodes;
+    type SerializeTupleStruct = SeqNodes;
+    type SerializeTupleVariant = Impossible<Self::Ok, WriteError>;
+    type SerializeMap = MapNodes;
+    type SerializeStruct = MapNodes;
+    type SerializeStructVariant = Impossible<Self::Ok, WriteError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, WriteError> {
+        scalar(if v { "yes" } else { "no" }.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, WriteError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, WriteError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, WriteError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, WriteError> {
+        scalar(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, WriteError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, WriteError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, WriteError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, WriteError> {
+        scalar(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, WriteError> {
+        // Through its own text, `0.1f32 as f64` would print 0.10000000149011612.
+        scalar(float(v.to_string().parse().unwrap_or(v.into())))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, WriteError> {
+        scalar(float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, WriteError> {
+        scalar(quote(&v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, WriteError> {
+        scalar(quote(v))
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, WriteError> {
+        unsupported("byte arrays")
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, WriteError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, WriteError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, WriteError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, WriteError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, WriteError> {
+        scalar(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, WriteError> {
+        let node = value.serialize(self)?;
+        if name != REPEATED {
+            return Ok(node);
+        }
+        Ok(node.map(|node| match node {
+            Node::Block(entries) => {
+                Node::Repeated(entries.into_iter().map(|entry| entry.node).collect())
+            }
+            node => node,
+        }))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, WriteError> {
+        unsupported("enum variants with data")
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqNodes, WriteError> {
+        Ok(SeqNodes(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqNodes, WriteError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<SeqNodes, WriteError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, WriteError> {
+        unsupported("enum variants with data")
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapNodes, WriteError> {
+        Ok(MapNodes {
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<MapNodes, WriteError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, WriteError> {
+        unsupported("enum variants with data")
+    }
+}
+
+/// Elements of a list. `None` elements are left out.
+struct SeqNodes(Vec<Entry<'static>>);
+
+impl SeqNodes {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        if let Some(node) = value.serialize(NodeSerializer)? {
+            self.0.push(Entry {
+                key: None,
+                node: node.nested(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SerializeSeq for SeqNodes {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, WriteError> {
+        Ok(Some(Node::Block(self.0)))
+    }
+}
+
+impl ser::SerializeTuple for SeqNodes {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, WriteError> {
+        Ok(Some(Node::Block(self.0)))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqNodes {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, WriteError> {
+        Ok(Some(Node::Block(self.0)))
+    }
+}
+
+/// Fields of a map or a struct. `None` values are left out with their key.
+struct MapNodes {
+    entries: Vec<Entry<'static>>,
+    key: Option<Cow<'static, str>>,
+}
+
+impl MapNodes {
+    fn push<T: Serialize + ?Sized>(
+        &mut self,
+        key: Cow<'static, str>,
+        value: &T,
+    ) -> Result<(), WriteError> {
+        if let Some(node) = value.serialize(NodeSerializer)? {
+            self.entries.push(Entry {
+                key: Some((key, Operator::Equal)),
+                node,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl SerializeMap for MapNodes {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), WriteError> {
+        // Ids and names, as written by the game.
+        let key = match serde_json::to_value(key).map_err(ser::Error::custom)? {
+            serde_json::Value::String(key) => Cow::Owned(key_text(&key).into_owned()),
+            serde_json::Value::Number(id) => Cow::Owned(id.to_string()),
+            _ => return unsupported("map keys other than strings and numbers"),
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| WriteError("map value without a key".to_string()))?;
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, WriteError> {
+        Ok(Some(Node::Block(self.entries)))
+    }
+}
+
+impl SerializeStruct for MapNodes {
+    type Ok = Option<Node<'static>>;
+    type Error = WriteError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), WriteError> {
+        self.push(key_text(key), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, WriteError> {
+        Ok(Some(Node::Block(self.entries)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jomini::{text::de::TextDeserializer, TextTape};
+
+    use super::*;
+    use crate::{models::gamestate_model::Gamestate, parser::parse_file};
+
+    #[test]
+    fn test_write_document() {
+        let value = parse_file(
+            r#"version="v3.8" flag={ colors={ "blue" "red" } icon={ category="human" } }
+            potential={ years_passed > 20 NOT={ has_flag?=x } }
+            color=rgb{ 255 0 0 } mixed={ 1 a=b } quoted="say \"hi\"" empty={}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            write_document(&value),
+            r#"version="v3.8"
+flag={
+	colors={ "blue" "red" }
+	icon={
+		category="human"
+	}
+}
+potential={
+	years_passed>20
+	NOT={
+		has_flag?=x
+	}
+}
+color=rgb { 255 0 0 }
+mixed={
+	1
+	a=b
+}
+quoted="say \"hi\""
+empty={ }
+"#
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("plain"), r#""plain""#);
+        assert_eq!(quote(r#"a "b""#), r#""a \"b\"""#);
+        assert_eq!(quote(r"back\slash\n"), r#""back\slash\n""#);
+        assert_eq!(quote(r#"a\"#), r#""a\\""#);
+        assert_eq!(quote(r#"a\\"#), r#""a\\\\""#);
+        assert_eq!(key_text("tech_lasers_1"), "tech_lasers_1");
+        assert_eq!(key_text("Two words"), r#""Two words""#);
+        assert_eq!(float(2.0), "2.0");
+        assert_eq!(float(-0.25), "-0.25");
+    }
+
+    #[test]
+    fn test_write_model() {
+        let parse = |text: &str| -> Gamestate {
+            let tape = TextTape::from_slice(text.as_bytes()).unwrap();
+            TextDeserializer::from_utf8_tape(&tape)
+                .deserialize()
+                .unwrap()
+        };
+        let gm = parse(
+            r#"version="Gemini v3.8.4" date="2210.01.01"
+            country={
+                0={ name={ key="%ADJ%" variables={ { key="adjective" value={ key="Human" } } } }
+                    military_power=12.5 victory_rank=2 war_allies={ 1 2 }
+                    tech_status={ technology="tech_a" level=1 technology="tech_b" level=3 }
+                    ethos={ ethic="ethic_a" ethic="ethic_b" } traditions={ "tr_a" "tr_b" }
+                    modules={ standard_economy_module={ resources={ energy=100 minerals=2.5 } } } }
+                2={}
+            }
+            fleet={ 7={ name={ key="Fleet \"One\"" literal=yes } station=yes ships={ 1 2 } } }
+            planets={ planet={ 3={ name={ key="Earth" } district="district_city" district="district_farming" } } }
+            market={ fluctuations={ 0 -10 5.5 } id={ 0 1 } resources_bought={ { 1 2 } { 3 4 } } }"#,
+        );
+
+        let text = to_text(&gm).unwrap();
+        assert!(text.starts_with("version=\"Gemini v3.8.4\"\ndate=\"2210.01.01\"\n"));
+        assert!(text.contains("\t\t\ttechnology=\"tech_a\"\n\t\t\ttechnology=\"tech_b\"\n"));
+        assert!(text
+            .contains("\t\t\tdistrict=\"district_city\"\n\t\t\tdistrict=\"district_farming\"\n"));
+        assert!(text.contains("\t\ttraditions={ \"tr_a\" \"tr_b\" }\n"));
+        assert!(text.contains("\t\t\tliteral=yes\n"));
+        assert!(!text.contains("tick="));
+
+        let written = parse(&text);
+        assert_eq!(
+            serde_json::to_value(&written).unwrap(),
+            serde_json::to_value(&gm).unwrap()
+        );
+        // The written text is valid for the built-in parser as well.
+        assert!(parse_file(&text).is_ok());
+    }
+}